@@ -0,0 +1,95 @@
+//! Scrollback buffer benchmarks
+//!
+//! Compares the chunked ring buffer against the previous `VecDeque<u8>`
+//! implementation (byte-by-byte extend, pop-front loop, copying history
+//! reads). Run with `cargo bench --bench scrollback`.
+
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::VecDeque;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/scrollback.rs"]
+mod scrollback;
+
+use scrollback::Scrollback;
+
+/// The scrollback implementation the ring buffer replaced
+struct VecDequeScrollback {
+    buf: VecDeque<u8>,
+    max_bytes: usize,
+}
+
+impl VecDequeScrollback {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            buf: VecDeque::new(),
+            max_bytes,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend(data.iter().copied());
+        while self.buf.len() > self.max_bytes {
+            self.buf.pop_front();
+        }
+    }
+
+    fn tail(&self, max: usize) -> Vec<u8> {
+        let skip = self.buf.len().saturating_sub(max);
+        self.buf.iter().skip(skip).copied().collect()
+    }
+}
+
+const SIZES: [usize; 2] = [64 * 1024, 4 * 1024 * 1024];
+
+/// Steady-state appends into a full buffer (every push evicts)
+fn bench_append(c: &mut Criterion) {
+    for chunk_len in [64usize, 16 * 1024] {
+        let chunk = Bytes::from(vec![b'x'; chunk_len]);
+        let mut group = c.benchmark_group(format!("append_{}b", chunk_len));
+        group.throughput(Throughput::Bytes(chunk_len as u64));
+
+        for size in SIZES {
+            group.bench_with_input(BenchmarkId::new("vecdeque", size), &size, |b, &size| {
+                let mut sb = VecDequeScrollback::new(size);
+                sb.push(&vec![b'x'; size]);
+                b.iter(|| sb.push(black_box(&chunk)));
+            });
+            group.bench_with_input(BenchmarkId::new("ring", size), &size, |b, &size| {
+                let mut sb = Scrollback::new(size);
+                sb.push(Bytes::from(vec![b'x'; size]));
+                b.iter(|| sb.push(black_box(chunk.clone())));
+            });
+        }
+        group.finish();
+    }
+}
+
+/// Reading the whole history (GetSessionHistory)
+fn bench_history(c: &mut Criterion) {
+    let chunk = Bytes::from(vec![b'x'; 4096]);
+    let mut group = c.benchmark_group("history");
+
+    for size in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+
+        let mut old = VecDequeScrollback::new(size);
+        let mut ring = Scrollback::new(size);
+        for _ in 0..(size / chunk.len() + 1) {
+            old.push(&chunk);
+            ring.push(chunk.clone());
+        }
+
+        group.bench_with_input(BenchmarkId::new("vecdeque", size), &size, |b, &size| {
+            b.iter(|| black_box(old.tail(size)));
+        });
+        group.bench_with_input(BenchmarkId::new("ring", size), &size, |b, &size| {
+            b.iter(|| black_box(ring.tail(size)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_append, bench_history);
+criterion_main!(benches);
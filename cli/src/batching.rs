@@ -0,0 +1,161 @@
+//! Adaptive batching of PTY output frames
+//!
+//! Interactive programs emit many tiny writes; sending each as its own
+//! WebSocket frame wastes radio time on mobile connections. Output that
+//! arrives after a quiet period (keystroke echo, a prompt) is flushed
+//! immediately so latency doesn't suffer; during a burst, writes are held
+//! until `max_delay` has passed or `max_bytes` have accumulated.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Longest a byte may wait in a batch (zero disables batching)
+    pub max_delay: Duration,
+    /// Flush as soon as a batch reaches this size
+    pub max_bytes: usize,
+}
+
+impl BatchConfig {
+    pub fn new(max_delay_ms: u64, max_bytes: usize) -> Self {
+        Self {
+            max_delay: Duration::from_millis(max_delay_ms),
+            max_bytes: max_bytes.max(1),
+        }
+    }
+
+    fn is_disabled(&self) -> bool {
+        self.max_delay.is_zero()
+    }
+}
+
+pub struct OutputBatcher {
+    config: BatchConfig,
+    pending: Vec<u8>,
+    /// When the first byte of the pending batch arrived
+    started: Option<Instant>,
+    /// When output was last pushed (to detect quiet periods)
+    last_output: Option<Instant>,
+}
+
+impl OutputBatcher {
+    pub fn new(config: BatchConfig) -> Self {
+        Self {
+            config,
+            pending: Vec::new(),
+            started: None,
+            last_output: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: BatchConfig) {
+        self.config = config;
+    }
+
+    /// Add output; returns a batch if it should be sent right away
+    pub fn push(&mut self, data: &[u8], now: Instant) -> Option<Vec<u8>> {
+        let idle = self
+            .last_output
+            .map_or(true, |t| now.duration_since(t) >= self.config.max_delay);
+        self.last_output = Some(now);
+        self.pending.extend_from_slice(data);
+
+        if self.config.is_disabled()
+            || (idle && self.started.is_none())
+            || self.pending.len() >= self.config.max_bytes
+        {
+            return self.flush();
+        }
+        self.started.get_or_insert(now);
+        None
+    }
+
+    /// Bytes waiting in the pending batch
+    pub fn pending_bytes(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// When the pending batch is due, if there is one
+    pub fn deadline(&self) -> Option<Instant> {
+        self.started.map(|t| t + self.config.max_delay)
+    }
+
+    /// Take the pending batch regardless of its deadline
+    pub fn flush(&mut self) -> Option<Vec<u8>> {
+        self.started = None;
+        if self.pending.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.pending))
+    }
+
+    /// Take the pending batch if its deadline has passed
+    pub fn flush_due(&mut self, now: Instant) -> Option<Vec<u8>> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.flush(),
+            _ => None,
+        }
+    }
+}
+
+/// Sleep until `deadline`; never completes when there is none (for `select!`)
+pub async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BatchConfig {
+        BatchConfig::new(10, 1024)
+    }
+
+    #[test]
+    fn test_output_after_quiet_period_is_immediate() {
+        let mut batcher = OutputBatcher::new(config());
+        let start = Instant::now();
+        assert_eq!(batcher.push(b"a", start), Some(b"a".to_vec()));
+        // Next keystroke echo well after the delay also goes straight out
+        let later = start + Duration::from_millis(50);
+        assert_eq!(batcher.push(b"b", later), Some(b"b".to_vec()));
+        assert!(batcher.deadline().is_none());
+    }
+
+    #[test]
+    fn test_burst_is_held_until_deadline() {
+        let mut batcher = OutputBatcher::new(config());
+        let start = Instant::now();
+        batcher.push(b"first", start);
+        let t1 = start + Duration::from_millis(1);
+        assert_eq!(batcher.push(b"12", t1), None);
+        assert_eq!(batcher.push(b"34", t1 + Duration::from_millis(1)), None);
+        assert_eq!(batcher.deadline(), Some(t1 + Duration::from_millis(10)));
+
+        assert_eq!(batcher.flush_due(t1 + Duration::from_millis(5)), None);
+        assert_eq!(
+            batcher.flush_due(t1 + Duration::from_millis(10)),
+            Some(b"1234".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_size_limit_flushes() {
+        let mut batcher = OutputBatcher::new(BatchConfig::new(10, 4));
+        let start = Instant::now();
+        batcher.push(b"x", start);
+        assert_eq!(batcher.push(b"ab", start), None);
+        assert_eq!(batcher.push(b"cd", start), Some(b"abcd".to_vec()));
+    }
+
+    #[test]
+    fn test_zero_delay_disables_batching() {
+        let mut batcher = OutputBatcher::new(BatchConfig::new(0, 1024));
+        let now = Instant::now();
+        assert_eq!(batcher.push(b"a", now), Some(b"a".to_vec()));
+        assert_eq!(batcher.push(b"b", now), Some(b"b".to_vec()));
+    }
+}
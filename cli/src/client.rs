@@ -0,0 +1,118 @@
+//! Local client connection to the daemon
+//!
+//! Used by CLI subcommands that talk to the daemon the same way the mobile
+//! app does (link, transcript, ...).
+
+use crate::daemon;
+use crate::protocol::{ClientMessage, ServerMessage, SessionListItem};
+use crate::pty_wrapper;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+pub type ClientError = Box<dyn std::error::Error>;
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// WebSocket URL of the local daemon
+pub fn daemon_url() -> String {
+    let port = daemon::get_port().unwrap_or(daemon::DEFAULT_PORT);
+    format!("ws://127.0.0.1:{}", port)
+}
+
+/// Connect to the local daemon, say hello and wait for the session list
+pub async fn connect() -> Result<(WsStream, Vec<SessionListItem>), ClientError> {
+    if !daemon::is_running() {
+        return Err("Daemon is not running. Start a session with 'mobilecli' first.".into());
+    }
+
+    let (mut ws, _) = connect_async(daemon_url()).await?;
+    send(
+        &mut ws,
+        &ClientMessage::Hello {
+            auth_token: None,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    )
+    .await?;
+
+    let sessions = recv_until(&mut ws, |msg| match msg {
+        ServerMessage::Sessions { sessions } => Some(sessions),
+        _ => None,
+    })
+    .await?
+    .unwrap_or_default();
+
+    Ok((ws, sessions))
+}
+
+/// Send a client message
+pub async fn send(ws: &mut WsStream, msg: &ClientMessage) -> Result<(), ClientError> {
+    ws.send(Message::Text(serde_json::to_string(msg)?)).await?;
+    Ok(())
+}
+
+/// Read server messages until `f` returns a value (None if the connection closes)
+pub async fn recv_until<T>(
+    ws: &mut WsStream,
+    mut f: impl FnMut(ServerMessage) -> Option<T>,
+) -> Result<Option<T>, ClientError> {
+    while let Some(msg) = ws.next().await {
+        match msg? {
+            Message::Text(text) => {
+                if let Ok(server_msg) = serde_json::from_str::<ServerMessage>(&text) {
+                    if let Some(value) = f(server_msg) {
+                        return Ok(Some(value));
+                    }
+                }
+            }
+            Message::Close(_) => break,
+            _ => continue,
+        }
+    }
+    Ok(None)
+}
+
+/// ID of the session this process runs in, if any
+pub fn current_session_id() -> Option<String> {
+    std::env::var(pty_wrapper::SESSION_ID_ENV)
+        .ok()
+        .filter(|id| !id.is_empty())
+}
+
+/// The session this process runs in, if the daemon still has it. The
+/// environment outlives the session in processes that were detached from
+/// it (tmux servers, nohup), so it alone isn't proof.
+pub async fn current_live_session() -> Option<SessionListItem> {
+    let session_id = current_session_id()?;
+    if !daemon::is_running() {
+        return None;
+    }
+    let (mut ws, sessions) = connect().await.ok()?;
+    let _ = ws.close(None).await;
+    sessions.into_iter().find(|s| s.session_id == session_id)
+}
+
+/// The given session, or the one this process runs in
+pub fn session_or_current(session: Option<&str>) -> Result<String, ClientError> {
+    match session {
+        Some(session) => Ok(session.to_string()),
+        None => current_session_id()
+            .ok_or_else(|| "Not inside a mobilecli session; pass --session".into()),
+    }
+}
+
+/// Find a session by ID prefix or (case-insensitive) name
+pub fn find_session<'a>(
+    sessions: &'a [SessionListItem],
+    id_or_name: &str,
+) -> Option<&'a SessionListItem> {
+    sessions
+        .iter()
+        .find(|s| s.session_id.starts_with(id_or_name))
+        .or_else(|| {
+            let needle = id_or_name.to_lowercase();
+            sessions
+                .iter()
+                .find(|s| s.name.to_lowercase().contains(&needle))
+        })
+}
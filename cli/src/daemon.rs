@@ -3,22 +3,40 @@
 //! Single WebSocket server that all terminal sessions stream to.
 //! Mobile connects once and sees all active sessions.
 
+use crate::batching::{self, BatchConfig, OutputBatcher};
 use crate::detection::{
-    detect_wait_event, strip_ansi_and_normalize, ApprovalModel, CliTracker, CliType, WaitType,
+    detect_wait_event, program_name, strip_ansi_and_normalize, ApprovalModel, CliTracker, CliType,
+    WaitType,
 };
+use crate::git::WorkDir;
+use crate::input_queue::{InputQueue, QueuedInput};
+use crate::output_log::{self, LogHeader, OutputLog};
 use crate::platform;
-use crate::protocol::{ClientMessage, ServerMessage, SessionListItem};
+use crate::protocol::{ClientDiagnostics, ClientMessage, ServerMessage, SessionListItem};
+use crate::recording::{self, CastHeader, CastRecorder};
+use crate::screen::ScreenState;
+use crate::scrollback::{self, Scrollback};
+use crate::search::{self, LiveLines, SearchQuery};
 use crate::session::{self, SessionInfo};
+use crate::setup::OverflowPolicy;
+use crate::shell_integration::{OscScanner, ShellEvent};
+use crate::sizing::{SizeArbiter, SizePolicy, SizeUpdate};
+use crate::transcript::{self, LineRenderer};
+use crate::triggers::{TriggerHit, TriggerRule, TriggerSet};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bytes::Bytes;
 use chrono::Utc;
 use futures_util::{SinkExt, StreamExt};
 use std::collections::{HashMap, VecDeque};
+use std::io::Write;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, Notify, RwLock};
 use tokio_tungstenite::{accept_async, tungstenite::Message};
 
 /// Shared HTTP client for push notifications (lazy initialized with timeout)
@@ -35,6 +53,9 @@ fn http_client() -> &'static reqwest::Client {
 /// Default WebSocket port
 pub const DEFAULT_PORT: u16 = 9847;
 
+/// Address the daemon listens on (all interfaces, so phones can connect)
+pub const BIND_ADDR: &str = "0.0.0.0";
+
 /// PID file path (cross-platform)
 fn pid_file() -> PathBuf {
     platform::config_dir().join("daemon.pid")
@@ -89,6 +110,13 @@ pub struct WaitingState {
     pub prompt_hash: u64,
 }
 
+/// A command reported running by shell integration
+#[derive(Debug, Clone)]
+pub struct RunningCommand {
+    pub command: Option<String>,
+    pub started_at: chrono::DateTime<Utc>,
+}
+
 /// Push notification token
 #[derive(Debug, Clone)]
 pub struct PushToken {
@@ -97,33 +125,116 @@ pub struct PushToken {
     pub platform: String,   // "ios" | "android"
 }
 
-/// Default scrollback buffer size (64KB)
-const DEFAULT_SCROLLBACK_MAX_BYTES: usize = 64 * 1024;
+/// Sessions are never trimmed below this by the daemon-wide scrollback budget
+const MIN_SCROLLBACK_BYTES: usize = 16 * 1024;
+
+/// Gap between typed text and the Enter that submits it. TUIs that read
+/// both in one go take them as a paste and don't submit.
+const SUBMIT_DELAY: Duration = Duration::from_millis(75);
 
 /// Active PTY session
+///
+/// The global `DaemonState` lock only guards the session map; everything that
+/// changes while the session runs lives in `SessionState` behind the
+/// session's own mutex, so busy sessions don't serialize each other.
 pub struct PtySession {
     pub session_id: String,
-    pub name: String,
     pub command: String,
     pub project_path: String,
     pub started_at: chrono::DateTime<Utc>,
     pub input_tx: mpsc::UnboundedSender<Vec<u8>>,
-    pub resize_tx: mpsc::UnboundedSender<(u16, u16)>,
+    pub resize_tx: mpsc::UnboundedSender<SizeUpdate>,
+    state: Mutex<SessionState>,
+}
+
+impl PtySession {
+    /// Lock the session's mutable state. Never held across an await.
+    pub fn lock(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send the effective size to the PTY if the arbiter's answer changed
+    pub fn apply_size(&self) {
+        if let Some(update) = self.lock().sizes.update() {
+            let _ = self.resize_tx.send(update);
+        }
+    }
+
+    /// Send the effective size to the PTY even if it didn't change: the
+    /// wrapper may have resized the PTY itself when the desktop was resized
+    pub fn resend_size(&self) {
+        let update = {
+            let mut inner = self.lock();
+            inner.sizes.update();
+            inner.sizes.current()
+        };
+        let _ = self.resize_tx.send(update);
+    }
+
+    /// Type `text`, then press Enter `SUBMIT_DELAY` later as a separate write
+    pub fn type_and_submit(&self, text: Vec<u8>) {
+        if !text.is_empty() {
+            let _ = self.input_tx.send(text);
+        }
+        let input_tx = self.input_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(SUBMIT_DELAY).await;
+            let _ = input_tx.send(b"\r".to_vec());
+        });
+    }
+}
+
+/// Mutable per-session state
+pub struct SessionState {
+    pub name: String,
     pub waiting_state: Option<WaitingState>,
     pub cli_tracker: CliTracker,
     pub last_wait_hash: Option<u64>,
     /// Scrollback buffer for session history (for linked terminals)
-    /// Uses VecDeque for efficient front truncation when buffer is full
-    pub scrollback: VecDeque<u8>,
-    /// Maximum scrollback buffer size
-    pub scrollback_max_bytes: usize,
+    pub scrollback: Scrollback,
+    /// Parsed VT screen, used for snapshots on subscribe
+    pub screen: ScreenState,
+    /// Total PTY output bytes seen so far (stream offset)
+    pub output_offset: u64,
+    /// Plain-text lines still on screen (not yet written to the output log)
+    pub live_lines: LineRenderer,
+    /// Incomplete UTF-8 sequence at the end of the last chunk, for `live_lines`
+    pub live_utf8: Vec<u8>,
+    /// Number of lines already committed to the output log
+    pub committed_lines: usize,
+    /// Desktop and viewer sizes, and the policy choosing between them
+    pub sizes: SizeArbiter,
+    /// Input held until the session is waiting
+    pub input_queue: InputQueue,
+    /// Command line of the PTY's foreground process, if the wrapper reports it
+    pub foreground_command: Option<String>,
+    /// Most recently reported working directory
+    pub cwd: Option<String>,
+    /// Working directory and git state as last announced to clients
+    pub workdir: Option<WorkDir>,
+    /// Command running at the shell prompt (shell integration only)
+    pub running_command: Option<RunningCommand>,
+    /// Long-running command threshold for this session (daemon default if None)
+    pub notify_command_after_secs: Option<u64>,
+    /// Regexes watched for in the output
+    pub triggers: TriggerSet,
+}
+
+/// A chunk of PTY output as fanned out to mobile clients
+#[derive(Debug, Clone)]
+pub struct PtyChunk {
+    pub session_id: String,
+    pub data: Bytes,
+    /// Stream offset just past this chunk; lets clients drop chunks that are
+    /// already part of a screen snapshot they received
+    pub end_offset: u64,
 }
 
 /// Daemon shared state
 pub struct DaemonState {
-    pub sessions: HashMap<String, PtySession>,
-    pub mobile_clients: HashMap<SocketAddr, mpsc::UnboundedSender<Message>>,
-    pub pty_broadcast: broadcast::Sender<(String, Vec<u8>)>,
+    pub sessions: HashMap<String, Arc<PtySession>>,
+    pub mobile_clients: HashMap<SocketAddr, MobileClient>,
+    pub pty_broadcast: broadcast::Sender<PtyChunk>,
     pub port: u16, // The actual port the daemon is running on
     pub push_tokens: Vec<PushToken>,
     pub mobile_views: HashMap<SocketAddr, std::collections::HashSet<String>>,
@@ -132,14 +243,154 @@ pub struct DaemonState {
     pub device_id: Option<String>,
     /// Device name (hostname)
     pub device_name: Option<String>,
+    /// Default per-session scrollback size
+    pub scrollback_default_bytes: usize,
+    /// Scrollback budget shared by all sessions
+    pub scrollback_budget: Arc<ScrollbackBudget>,
+    /// Default output batching for mobile connections
+    pub batch_config: BatchConfig,
+    /// What to do when a mobile client falls behind
+    pub overflow_policy: OverflowPolicy,
+    /// Size policy for new sessions
+    pub size_policy: SizePolicy,
+    /// Default long-running command threshold in seconds (0 = never notify)
+    pub notify_command_after_secs: u64,
+    /// Triggers every new session starts with
+    pub trigger_rules: Vec<TriggerRule>,
+}
+
+/// Control messages buffered per mobile client before it counts as overflowed.
+/// PTY output is bounded separately by the `pty_broadcast` capacity.
+const CLIENT_QUEUE_CAPACITY: usize = 256;
+
+/// Events held for an overflowed client until its resync; beyond this the
+/// oldest are dropped
+const MAX_HELD_EVENTS: usize = 1024;
+
+/// A connected mobile client, as seen from other tasks
+pub struct MobileClient {
+    tx: mpsc::Sender<Queued>,
+    pub stats: Arc<ClientStats>,
+}
+
+/// A message in a client's control queue
+struct Queued {
+    msg: Message,
+    /// Sent with `send_event`: kept when a resync discards the queue
+    event: bool,
+}
+
+impl MobileClient {
+    /// Queue a message without waiting; a full queue flags the client as
+    /// overflowed and its task applies the overflow policy
+    pub fn send(&self, msg: Message) {
+        let queued = Queued { msg, event: false };
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.tx.try_send(queued) {
+            self.stats.overflowed.notify_one();
+        }
+    }
+
+    /// Like `send`, for events a resync can't reconstruct (a command
+    /// finished, a trigger matched, ...): if the queue is full the event is
+    /// held and sent after the resync instead of being dropped
+    pub fn send_event(&self, msg: Message) {
+        let queued = Queued { msg, event: true };
+        if let Err(mpsc::error::TrySendError::Full(queued)) = self.tx.try_send(queued) {
+            let mut held = self.stats.held_events();
+            if held.len() >= MAX_HELD_EVENTS {
+                held.pop_front();
+            }
+            held.push_back(queued.msg);
+            drop(held);
+            self.stats.overflowed.notify_one();
+        }
+    }
+
+    /// Control messages waiting in the queue
+    pub fn queued_messages(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+}
+
+/// Queue statistics shared between a client's task and the rest of the daemon
+pub struct ClientStats {
+    pub connected_at: chrono::DateTime<Utc>,
+    pub queued_output: AtomicUsize,
+    pub pending_batch_bytes: AtomicUsize,
+    pub overflows: AtomicU64,
+    overflowed: Notify,
+    /// Events that didn't fit in the queue, sent after the next resync
+    held_events: Mutex<VecDeque<Message>>,
+}
+
+impl ClientStats {
+    fn new() -> Self {
+        Self {
+            connected_at: Utc::now(),
+            queued_output: AtomicUsize::new(0),
+            pending_batch_bytes: AtomicUsize::new(0),
+            overflows: AtomicU64::new(0),
+            overflowed: Notify::new(),
+            held_events: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn held_events(&self) -> MutexGuard<'_, VecDeque<Message>> {
+        self.held_events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Daemon-wide scrollback accounting. Session tasks keep a handle so the
+/// per-chunk path can check the budget without taking the global lock.
+pub struct ScrollbackBudget {
+    pub max_bytes: usize,
+    used: AtomicUsize,
+}
+
+impl ScrollbackBudget {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Account for a session's scrollback changing size
+    fn update(&self, before: usize, after: usize) {
+        if after >= before {
+            self.used.fetch_add(after - before, Ordering::Relaxed);
+        } else {
+            self.release(before - after);
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Bytes over the budget (0 if within it)
+    fn excess(&self) -> usize {
+        self.used
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.max_bytes)
+    }
 }
 
 impl DaemonState {
     pub fn new(port: u16) -> Self {
         let (pty_broadcast, _) = broadcast::channel(256);
 
-        // Load device info from config
-        let (device_id, device_name) = crate::setup::load_config()
+        // Load device info and settings from config
+        let config = crate::setup::load_config();
+        let settings = config
+            .as_ref()
+            .map(|c| c.settings.clone())
+            .unwrap_or_default();
+        let (device_id, device_name) = config
             .map(|c| (Some(c.device_id), Some(c.device_name)))
             .unwrap_or((None, None));
 
@@ -153,8 +404,93 @@ impl DaemonState {
             session_view_counts: HashMap::new(),
             device_id,
             device_name,
+            scrollback_default_bytes: settings.scrollback_bytes,
+            scrollback_budget: Arc::new(ScrollbackBudget::new(settings.scrollback_total_max_bytes)),
+            batch_config: BatchConfig::new(settings.output_batch_ms, settings.output_batch_bytes),
+            overflow_policy: settings.slow_client_policy,
+            size_policy: settings.size_policy,
+            notify_command_after_secs: settings.notify_command_after_secs,
+            trigger_rules: settings.triggers,
+        }
+    }
+
+    /// Look up a session; the map lock can be released right after
+    pub fn session(&self, session_id: &str) -> Option<Arc<PtySession>> {
+        self.sessions.get(session_id).cloned()
+    }
+
+    /// Trim scrollback until the daemon-wide budget is met.
+    ///
+    /// Eviction policy: bytes are taken from the front of whichever session
+    /// currently holds the most scrollback, so one noisy session cannot push
+    /// out everybody else's history. Sessions are never trimmed below
+    /// `MIN_SCROLLBACK_BYTES`.
+    fn enforce_scrollback_budget(&self) {
+        while self.scrollback_budget.excess() > 0 {
+            let Some(largest) = self
+                .sessions
+                .values()
+                .map(|s| (s.lock().scrollback.len(), s))
+                .filter(|(len, _)| *len > MIN_SCROLLBACK_BYTES)
+                .max_by_key(|(len, _)| *len)
+                .map(|(_, s)| s)
+            else {
+                break;
+            };
+            let mut session = largest.lock();
+            let evict = self.scrollback_budget.excess().min(
+                session
+                    .scrollback
+                    .len()
+                    .saturating_sub(MIN_SCROLLBACK_BYTES),
+            );
+            if evict == 0 {
+                break;
+            }
+            session.scrollback.evict_front(evict);
+            self.scrollback_budget.release(evict);
         }
     }
+
+    /// Session list as sent to clients
+    fn session_list_items(&self) -> Vec<SessionListItem> {
+        self.sessions
+            .values()
+            .map(|s| {
+                let inner = s.lock();
+                let workdir = inner.workdir.as_ref();
+                let git = workdir.and_then(|w| w.git.as_ref());
+                SessionListItem {
+                    session_id: s.session_id.clone(),
+                    name: inner.name.clone(),
+                    command: s.command.clone(),
+                    project_path: s.project_path.clone(),
+                    ws_port: self.port,
+                    started_at: s.started_at.to_rfc3339(),
+                    cli_type: inner.cli_tracker.current().as_str().to_string(),
+                    scrollback_bytes: inner.scrollback.len(),
+                    scrollback_max_bytes: inner.scrollback.max_bytes(),
+                    size_policy: inner.sizes.policy().as_str().to_string(),
+                    waiting_state: inner
+                        .waiting_state
+                        .as_ref()
+                        .map(|w| w.wait_type.as_str().to_string()),
+                    foreground_command: inner.foreground_command.clone(),
+                    notify_command_after_secs: inner
+                        .notify_command_after_secs
+                        .unwrap_or(self.notify_command_after_secs),
+                    cwd: workdir.map(|w| w.path.clone()),
+                    git_branch: git.map(|g| g.branch.clone()),
+                    git_dirty: git.map(|g| g.dirty),
+                    viewers: self
+                        .session_view_counts
+                        .get(&s.session_id)
+                        .copied()
+                        .unwrap_or(0),
+                }
+            })
+            .collect()
+    }
 }
 
 pub type SharedState = Arc<RwLock<DaemonState>>;
@@ -174,13 +510,16 @@ pub async fn run(port: u16) -> std::io::Result<()> {
 
     let state: SharedState = Arc::new(RwLock::new(DaemonState::new(port)));
 
+    // Drop session logs past their retention period
+    tokio::task::spawn_blocking(output_log::prune_old_logs);
+
     // Start WebSocket server on all interfaces (0.0.0.0)
     // This is intentional - mobile clients need network access to connect.
     // Security model: Access is controlled at the network level via:
     // - Local network: Only devices on same WiFi can connect
     // - Tailscale: Only authenticated Tailscale network members can connect
     // Users explicitly choose their connection mode in setup wizard.
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let listener = TcpListener::bind(format!("{}:{}", BIND_ADDR, port)).await?;
     tracing::info!("Daemon WebSocket server on port {}", port);
 
     // Run the main loop with platform-specific signal handling
@@ -279,6 +618,72 @@ async fn handle_connection(
     }
 }
 
+/// Per-connection state of a mobile client
+struct ClientConnection {
+    /// Stream offsets already covered by screen snapshots sent to this client
+    snapshot_offsets: HashMap<String, u64>,
+    batch_config: BatchConfig,
+    /// Output waiting to be sent, per session
+    batches: HashMap<String, OutputBatcher>,
+}
+
+impl ClientConnection {
+    fn new(batch_config: BatchConfig) -> Self {
+        Self {
+            snapshot_offsets: HashMap::new(),
+            batch_config,
+            batches: HashMap::new(),
+        }
+    }
+
+    fn set_batch_config(&mut self, config: BatchConfig) {
+        self.batch_config = config;
+        for batcher in self.batches.values_mut() {
+            batcher.set_config(config);
+        }
+    }
+
+    /// Add session output; returns data to send right away, if any
+    fn push_output(&mut self, session_id: &str, data: &[u8], now: Instant) -> Option<Vec<u8>> {
+        let config = self.batch_config;
+        self.batches
+            .entry(session_id.to_string())
+            .or_insert_with(|| OutputBatcher::new(config))
+            .push(data, now)
+    }
+
+    /// Earliest deadline of any pending batch
+    fn next_deadline(&self) -> Option<Instant> {
+        self.batches.values().filter_map(|b| b.deadline()).min()
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<(String, Vec<u8>)> {
+        self.batches
+            .iter_mut()
+            .filter_map(|(id, b)| b.flush_due(now).map(|data| (id.clone(), data)))
+            .collect()
+    }
+
+    fn take_all(&mut self) -> Vec<(String, Vec<u8>)> {
+        self.batches
+            .iter_mut()
+            .filter_map(|(id, b)| b.flush().map(|data| (id.clone(), data)))
+            .collect()
+    }
+
+    fn pending_bytes(&self) -> usize {
+        self.batches.values().map(|b| b.pending_bytes()).sum()
+    }
+
+    /// Drop pending output; returns the sessions this client has received output for
+    fn discard_pending(&mut self) -> Vec<String> {
+        for batcher in self.batches.values_mut() {
+            batcher.flush();
+        }
+        self.batches.keys().cloned().collect()
+    }
+}
+
 /// Handle mobile client connection
 async fn handle_mobile_client(
     first_msg: Option<String>,
@@ -289,13 +694,20 @@ async fn handle_mobile_client(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing::info!("Mobile client connected: {}", addr);
 
-    let (client_tx, mut client_rx) = mpsc::unbounded_channel::<Message>();
+    let (client_tx, mut client_rx) = mpsc::channel::<Queued>(CLIENT_QUEUE_CAPACITY);
+    let stats = Arc::new(ClientStats::new());
 
     // Register client and get broadcast receiver
-    let mut pty_rx = {
+    let (mut pty_rx, overflow_policy) = {
         let mut st = state.write().await;
-        st.mobile_clients.insert(addr, client_tx);
-        st.pty_broadcast.subscribe()
+        st.mobile_clients.insert(
+            addr,
+            MobileClient {
+                tx: client_tx,
+                stats: stats.clone(),
+            },
+        );
+        (st.pty_broadcast.subscribe(), st.overflow_policy)
     };
 
     // Send welcome with device info
@@ -318,35 +730,71 @@ async fn handle_mobile_client(
     // Send current waiting states for all sessions (for late-joining clients)
     send_waiting_states(&state, &mut tx).await?;
 
+    let mut conn = ClientConnection::new(state.read().await.batch_config);
+
     // Process first message if it was a client message
     if let Some(text) = first_msg {
         if let Ok(msg) = serde_json::from_str::<ClientMessage>(&text) {
-            process_client_msg(msg, &state, &mut tx, addr).await?;
+            process_client_msg(msg, &state, &mut tx, addr, &mut conn).await?;
         }
     }
 
     loop {
+        stats.queued_output.store(pty_rx.len(), Ordering::Relaxed);
+        stats
+            .pending_batch_bytes
+            .store(conn.pending_bytes(), Ordering::Relaxed);
+
         tokio::select! {
             // PTY output
             result = pty_rx.recv() => {
                 match result {
-                    Ok((session_id, data)) => {
-                        let msg = ServerMessage::PtyBytes {
-                            session_id,
-                            data: BASE64.encode(&data),
-                        };
-                        if tx.send(Message::Text(serde_json::to_string(&msg)?)).await.is_err() {
+                    Ok(chunk) => {
+                        if conn
+                            .snapshot_offsets
+                            .get(&chunk.session_id)
+                            .is_some_and(|floor| chunk.end_offset <= *floor)
+                        {
+                            // Already included in the snapshot this client received
+                            continue;
+                        }
+                        if let Some(batch) = conn.push_output(&chunk.session_id, &chunk.data, Instant::now()) {
+                            if send_pty_bytes(&mut tx, chunk.session_id, &batch).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Client {} lagged by {} chunks", addr, skipped);
+                        if handle_overflow(&state, &mut tx, &mut client_rx, addr, &mut conn, &stats, overflow_policy).await.is_err() {
                             break;
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
                     Err(_) => break,
                 }
             }
 
-            // Queued messages
-            Some(msg) = client_rx.recv() => {
-                if tx.send(msg).await.is_err() {
+            // Control queue overflowed (flagged by MobileClient::send)
+            _ = stats.overflowed.notified() => {
+                tracing::debug!("Client {} control queue overflowed", addr);
+                if handle_overflow(&state, &mut tx, &mut client_rx, addr, &mut conn, &stats, overflow_policy).await.is_err() {
+                    break;
+                }
+            }
+
+            // Batched output is due
+            _ = batching::sleep_until(conn.next_deadline()) => {
+                if send_batches(&mut tx, conn.take_due(Instant::now())).await.is_err() {
+                    break;
+                }
+            }
+
+            // Queued messages (pending output goes first to keep ordering)
+            Some(queued) = client_rx.recv() => {
+                if send_batches(&mut tx, conn.take_all()).await.is_err() {
+                    break;
+                }
+                if tx.send(queued.msg).await.is_err() {
                     break;
                 }
             }
@@ -356,7 +804,10 @@ async fn handle_mobile_client(
                 match result {
                     Some(Ok(Message::Text(text))) => {
                         if let Ok(msg) = serde_json::from_str::<ClientMessage>(&text) {
-                            process_client_msg(msg, &state, &mut tx, addr).await?;
+                            if send_batches(&mut tx, conn.take_all()).await.is_err() {
+                                break;
+                            }
+                            process_client_msg(msg, &state, &mut tx, addr, &mut conn).await?;
                         }
                     }
                     Some(Ok(Message::Ping(d))) => { let _ = tx.send(Message::Pong(d)).await; }
@@ -388,53 +839,126 @@ async fn handle_pty_session(
         .filter(|s| !s.is_empty())
         .ok_or("Missing or empty session_id in registration")?
         .to_string();
+    if !session::is_valid_session_id(&session_id) {
+        return Err(format!("Invalid session_id in registration: {}", session_id).into());
+    }
     let name = reg_msg["name"].as_str().unwrap_or("Terminal").to_string();
     let command = reg_msg["command"].as_str().unwrap_or("shell").to_string();
     let project_path = reg_msg["project_path"].as_str().unwrap_or("").to_string();
+    let cols = reg_msg["cols"].as_u64().unwrap_or(80) as u16;
+    let rows = reg_msg["rows"].as_u64().unwrap_or(24) as u16;
+    let scrollback_bytes = reg_msg["scrollback_bytes"].as_u64().map(|n| n as usize);
+    let notify_command_after_secs = reg_msg["notify_after_secs"].as_u64();
+    let settings = crate::setup::load_settings();
+    let record = reg_msg["record"].as_bool().unwrap_or(false) || settings.record_sessions;
 
     tracing::info!("PTY session registered: {} ({})", name, session_id);
 
+    // Asciicast recording, fed from the same stream as pty_broadcast
+    let mut recorder = if record {
+        match CastRecorder::create(
+            &session_id,
+            CastHeader {
+                cols,
+                rows,
+                title: &name,
+                command: &command,
+            },
+        ) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                tracing::warn!("Failed to start recording for {}: {}", session_id, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Searchable plain-text log of the session output
+    let started_at = Utc::now();
+    let mut output_log = if settings.output_logs {
+        match OutputLog::create(LogHeader {
+            session_id: session_id.clone(),
+            name: name.clone(),
+            command: command.clone(),
+            started_at,
+        }) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                tracing::warn!("Failed to create output log for {}: {}", session_id, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
-    let (resize_tx, mut resize_rx) = mpsc::unbounded_channel::<(u16, u16)>();
+    let (resize_tx, mut resize_rx) = mpsc::unbounded_channel::<SizeUpdate>();
 
     // Register session
-    let pty_broadcast = {
+    let (session, pty_broadcast) = {
         let mut cli_tracker = CliTracker::new();
         cli_tracker.update_from_command(&command);
 
         let mut st = state.write().await;
-        st.sessions.insert(
-            session_id.clone(),
-            PtySession {
-                session_id: session_id.clone(),
+        // A single session may use at most the whole daemon budget
+        let scrollback_max_bytes = scrollback_bytes
+            .unwrap_or(st.scrollback_default_bytes)
+            .min(st.scrollback_budget.max_bytes);
+        let session = Arc::new(PtySession {
+            session_id: session_id.clone(),
+            command,
+            project_path,
+            started_at,
+            input_tx,
+            resize_tx,
+            state: Mutex::new(SessionState {
                 name: name.clone(),
-                command,
-                project_path,
-                started_at: Utc::now(),
-                input_tx,
-                resize_tx,
                 waiting_state: None,
                 cli_tracker,
                 last_wait_hash: None,
-                scrollback: VecDeque::new(),
-                scrollback_max_bytes: DEFAULT_SCROLLBACK_MAX_BYTES,
-            },
-        );
-        st.pty_broadcast.clone()
+                scrollback: Scrollback::new(scrollback_max_bytes),
+                screen: ScreenState::new(cols, rows),
+                output_offset: 0,
+                live_lines: LineRenderer::new(rows),
+                live_utf8: Vec::new(),
+                committed_lines: 0,
+                sizes: SizeArbiter::new(st.size_policy, (cols, rows)),
+                input_queue: InputQueue::default(),
+                foreground_command: None,
+                cwd: None,
+                workdir: None,
+                running_command: None,
+                notify_command_after_secs,
+                triggers: TriggerSet::from_rules(&st.trigger_rules),
+            }),
+        });
+        st.sessions.insert(session_id.clone(), session.clone());
+        (session, st.pty_broadcast.clone())
     };
+    let scrollback_budget = state.read().await.scrollback_budget.clone();
 
     // Notify mobile clients and persist to file
     broadcast_sessions_update(&state).await;
     persist_sessions_to_file(&state).await;
 
-    // Send ACK
-    tx.send(Message::Text(r#"{"type":"registered"}"#.to_string()))
-        .await?;
+    // Send ACK (with recording path so the wrapper can tell the user)
+    let ack = serde_json::json!({
+        "type": "registered",
+        "recording": recorder.as_ref().map(|r| r.path().display().to_string()),
+        "desktop_decides": session.lock().sizes.desktop_decides(),
+    });
+    tx.send(Message::Text(ack.to_string())).await?;
 
     // Buffer for detecting waiting state patterns (ANSI-stripped, normalized)
     let mut output_buffer = String::new();
     const BUFFER_MAX_CHARS: usize = 4000; // Keep last N chars for pattern matching
 
+    // Shell integration sequences (current directory)
+    let mut osc = OscScanner::default();
+
     loop {
         tokio::select! {
             // PTY output from terminal wrapper
@@ -445,20 +969,12 @@ async fn handle_pty_session(
                             if msg["type"].as_str() == Some("pty_output") {
                                 if let Some(data) = msg["data"].as_str() {
                                     if let Ok(bytes) = BASE64.decode(data) {
-                                        let _ = pty_broadcast.send((session_id.clone(), bytes.clone()));
-
-                                        // Accumulate scrollback for session history (linked terminals)
-                                        // Uses VecDeque for efficient front truncation
-                                        {
-                                            let mut st = state.write().await;
-                                            if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                session.scrollback.extend(bytes.iter().copied());
-                                                // Truncate from front if over limit (VecDeque is O(1) per pop)
-                                                while session.scrollback.len() > session.scrollback_max_bytes {
-                                                    session.scrollback.pop_front();
-                                                }
-                                            }
+                                        let bytes = Bytes::from(bytes);
+                                        record_output(&mut recorder, &bytes);
+                                        for event in osc.feed(&bytes) {
+                                            handle_shell_event(&state, &session, event).await;
                                         }
+                                        ingest_output(&state, &session, &pty_broadcast, &scrollback_budget, bytes.clone(), &mut output_log).await;
 
                                         let text = String::from_utf8_lossy(&bytes);
                                         let normalized_chunk = strip_ansi_and_normalize(&text);
@@ -467,42 +983,50 @@ async fn handle_pty_session(
                                             output_buffer.push_str(&normalized_chunk);
                                             truncate_to_max_chars(&mut output_buffer, BUFFER_MAX_CHARS);
 
+                                            // Where this chunk starts in the buffer (0 if truncation cut into it)
+                                            let new_from = output_buffer.len().saturating_sub(normalized_chunk.len());
+
                                             // Update CLI tracker based on output
-                                            let cli_type = {
-                                                let mut st = state.write().await;
-                                                if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                    session.cli_tracker.update_from_output(&normalized_chunk);
-                                                    session.cli_tracker.current()
-                                                } else {
-                                                    CliType::Terminal
-                                                }
+                                            let (cli_type, hits) = {
+                                                let mut inner = session.lock();
+                                                inner.cli_tracker.update_from_output(&normalized_chunk);
+                                                let cli_type = inner.cli_tracker.current();
+                                                (cli_type, inner.triggers.check(&output_buffer, new_from, cli_type))
                                             };
+                                            for hit in hits {
+                                                trigger_matched(&state, &session, hit).await;
+                                            }
 
                                             // Check for waiting state patterns
                                             if let Some(wait_event) = detect_wait_event(&output_buffer, cli_type) {
+                                                let mut queued = None;
                                                 let should_notify = {
-                                                    let mut st = state.write().await;
-                                                    if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                        let is_new = session.waiting_state.as_ref().map(|w| {
-                                                            w.prompt_hash != wait_event.prompt_hash || w.wait_type != wait_event.wait_type
-                                                        }).unwrap_or(true);
-                                                        if is_new {
-                                                            session.waiting_state = Some(WaitingState {
-                                                                wait_type: wait_event.wait_type,
-                                                                prompt_content: wait_event.prompt.clone(),
-                                                                timestamp: Utc::now(),
-                                                                approval_model: wait_event.approval_model,
-                                                                prompt_hash: wait_event.prompt_hash,
-                                                            });
-                                                            session.last_wait_hash = Some(wait_event.prompt_hash);
-                                                        }
-                                                        is_new
-                                                    } else {
-                                                        false
+                                                    let mut inner = session.lock();
+                                                    let is_new = inner.waiting_state.as_ref().map(|w| {
+                                                        w.prompt_hash != wait_event.prompt_hash || w.wait_type != wait_event.wait_type
+                                                    }).unwrap_or(true);
+                                                    if is_new {
+                                                        queued = inner.input_queue.take_ready(wait_event.wait_type);
                                                     }
+                                                    if is_new && queued.is_none() {
+                                                        inner.waiting_state = Some(WaitingState {
+                                                            wait_type: wait_event.wait_type,
+                                                            prompt_content: wait_event.prompt.clone(),
+                                                            timestamp: Utc::now(),
+                                                            approval_model: wait_event.approval_model,
+                                                            prompt_hash: wait_event.prompt_hash,
+                                                        });
+                                                        inner.last_wait_hash = Some(wait_event.prompt_hash);
+                                                    }
+                                                    is_new && queued.is_none()
                                                 };
 
-                                                if should_notify {
+                                                if let Some(item) = queued {
+                                                    // Answered from the queue: nobody needs to be told
+                                                    // it was waiting, and the prompt must not match again
+                                                    output_buffer.clear();
+                                                    deliver_queued_input(&state, &session, item, wait_event.wait_type).await;
+                                                } else if should_notify {
                                                     // Broadcast to mobile clients
                                                     broadcast_waiting_for_input(&state, &session_id).await;
 
@@ -512,24 +1036,27 @@ async fn handle_pty_session(
                                                         st.push_tokens.clone()
                                                     };
                                                     let session_id_clone = session_id.clone();
-                                                    let name_clone = name.clone();
+                                                    let name_clone = session.lock().name.clone();
                                                     tokio::spawn(async move {
                                                         let (title, body) = build_notification_text(cli_type, &name_clone, &wait_event);
-                                                        send_push_notifications(&tokens, &title, &body, &session_id_clone).await;
+                                                        let push = PushNotification {
+                                                            title,
+                                                            body,
+                                                            session_id: Some(session_id_clone),
+                                                            kind: "waiting_for_input",
+                                                            priority: "high",
+                                                        };
+                                                        send_push_notifications(&tokens, &push).await;
                                                     });
                                                 }
                                             } else {
                                                 // If previously waiting, clear on meaningful output that is not a waiting prompt
                                                 let should_clear = {
-                                                    let mut st = state.write().await;
-                                                    if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                        if session.waiting_state.is_some() && normalized_chunk.trim().chars().count() >= 10 {
-                                                            session.waiting_state = None;
-                                                            session.last_wait_hash = None;
-                                                            true
-                                                        } else {
-                                                            false
-                                                        }
+                                                    let mut inner = session.lock();
+                                                    if inner.waiting_state.is_some() && normalized_chunk.trim().chars().count() >= 10 {
+                                                        inner.waiting_state = None;
+                                                        inner.last_wait_hash = None;
+                                                        true
                                                     } else {
                                                         false
                                                     }
@@ -542,6 +1069,58 @@ async fn handle_pty_session(
                                         }
                                     }
                                 }
+                            } else if msg["type"].as_str() == Some("output_gap") {
+                                // The wrapper dropped output while we were behind; mark the
+                                // gap in everything fed from the stream. The wrapper has the
+                                // program redraw, which repairs the screen.
+                                let dropped = msg["dropped_bytes"].as_u64().unwrap_or(0);
+                                tracing::warn!("Session {} lost {} bytes of output", session_id, dropped);
+                                osc = OscScanner::default();
+                                session.lock().live_utf8.clear();
+                                let marker = Bytes::from(format!(
+                                    "\r\n\x1b[0m[mobilecli: {} bytes of output skipped]\r\n",
+                                    dropped
+                                ));
+                                record_output(&mut recorder, &marker);
+                                ingest_output(&state, &session, &pty_broadcast, &scrollback_budget, marker, &mut output_log).await;
+                            } else if msg["type"].as_str() == Some("pty_resized") {
+                                if let (Some(cols), Some(rows)) = (msg["cols"].as_u64(), msg["rows"].as_u64()) {
+                                    session.lock().screen.resize(cols as u16, rows as u16);
+                                    if let Some(r) = recorder.as_mut() {
+                                        if let Err(e) = r.write_resize(cols as u16, rows as u16) {
+                                            tracing::warn!("Recording write failed for {}: {}", session_id, e);
+                                            recorder = None;
+                                        }
+                                    }
+                                    broadcast_pty_resized(&state, &session_id, cols as u16, rows as u16).await;
+                                }
+                            } else if msg["type"].as_str() == Some("local_resize") {
+                                if let (Some(cols), Some(rows)) = (msg["cols"].as_u64(), msg["rows"].as_u64()) {
+                                    session.lock().sizes.set_desktop((cols as u16, rows as u16));
+                                    session.resend_size();
+                                }
+                            } else if msg["type"].as_str() == Some("foreground") {
+                                let argv: Vec<String> = msg["argv"]
+                                    .as_array()
+                                    .map(|args| args.iter().filter_map(|a| a.as_str().map(String::from)).collect())
+                                    .unwrap_or_default();
+                                if let Some(program) = program_name(&argv) {
+                                    {
+                                        let mut inner = session.lock();
+                                        inner.foreground_command = Some(argv.join(" "));
+                                        inner.cli_tracker.update_from_foreground(&program);
+                                    }
+                                    tracing::debug!("Session {} foreground: {}", session_id, program);
+                                    broadcast_sessions_update(&state).await;
+                                }
+                                // A command starting or finishing may also have changed
+                                // the directory or the repository
+                                if let Some(cwd) = msg["cwd"].as_str() {
+                                    report_cwd(&state, &session, cwd.to_string());
+                                }
+                            } else if msg["type"].as_str() == Some("local_active") {
+                                session.lock().sizes.touch_desktop();
+                                session.apply_size();
                             } else if msg["type"].as_str() == Some("session_ended") {
                                 exit_code = msg["exit_code"].as_i64().unwrap_or(0) as i32;
                                 tracing::info!("PTY session {} ended (exit_code={})", session_id, exit_code);
@@ -550,7 +1129,11 @@ async fn handle_pty_session(
                         }
                     }
                     Some(Ok(Message::Binary(data))) => {
-                        let _ = pty_broadcast.send((session_id.clone(), data));
+                        record_output(&mut recorder, &data);
+                        for event in osc.feed(&data) {
+                            handle_shell_event(&state, &session, event).await;
+                        }
+                        ingest_output(&state, &session, &pty_broadcast, &scrollback_budget, Bytes::from(data), &mut output_log).await;
                     }
                     Some(Ok(Message::Close(_))) | None => break,
                     _ => {}
@@ -568,16 +1151,16 @@ async fn handle_pty_session(
                 }
 
                 // Clear waiting state when user sends input
-                {
-                    let mut st = state.write().await;
-                    if let Some(session) = st.sessions.get_mut(&session_id) {
-                        if session.waiting_state.is_some() {
-                            session.waiting_state = None;
-                            session.last_wait_hash = None;
-                            drop(st);
-                            broadcast_waiting_cleared(&state, &session_id).await;
-                        }
+                let cleared = {
+                    let mut inner = session.lock();
+                    let was_waiting = inner.waiting_state.take().is_some();
+                    if was_waiting {
+                        inner.last_wait_hash = None;
                     }
+                    was_waiting
+                };
+                if cleared {
+                    broadcast_waiting_cleared(&state, &session_id).await;
                 }
 
                 // Clear output buffer on input
@@ -585,11 +1168,12 @@ async fn handle_pty_session(
             }
 
             // Resize from mobile
-            Some((cols, rows)) = resize_rx.recv() => {
+            Some(update) = resize_rx.recv() => {
                 let msg = serde_json::json!({
                     "type": "resize",
-                    "cols": cols,
-                    "rows": rows,
+                    "cols": update.size.0,
+                    "rows": update.size.1,
+                    "desktop_decides": update.desktop_decides,
                 });
                 if tx.send(Message::Text(msg.to_string())).await.is_err() {
                     break;
@@ -599,18 +1183,29 @@ async fn handle_pty_session(
     }
 
     // Unregister session
+    state.write().await.sessions.remove(&session_id);
     {
-        let mut st = state.write().await;
-        st.sessions.remove(&session_id);
+        let inner = session.lock();
+        scrollback_budget.release(inner.scrollback.len());
+        // Whatever is left on screen is final now
+        write_output_log(
+            &mut output_log,
+            &inner.live_lines.lines(),
+            inner.committed_lines,
+            inner.output_offset,
+        );
+    }
 
-        // Notify about session end
+    // Notify about session end
+    {
+        let st = state.read().await;
         let msg = ServerMessage::SessionEnded {
             session_id: session_id.clone(),
             exit_code,
         };
         let msg_str = serde_json::to_string(&msg)?;
         for client in st.mobile_clients.values() {
-            let _ = client.send(Message::Text(msg_str.clone()));
+            client.send(Message::Text(msg_str.clone()));
         }
     }
 
@@ -633,6 +1228,7 @@ async fn process_client_msg(
         Message,
     >,
     addr: SocketAddr,
+    conn: &mut ClientConnection,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     match msg {
         ClientMessage::Hello { client_version, .. } => {
@@ -641,41 +1237,58 @@ async fn process_client_msg(
         }
         ClientMessage::Subscribe { session_id } => {
             tracing::debug!("Client subscribed to session: {}", session_id);
-            let mut st = state.write().await;
-            let entry = st.mobile_views.entry(addr).or_default();
-            if entry.insert(session_id.clone()) {
-                let count = st
-                    .session_view_counts
-                    .entry(session_id.clone())
-                    .or_insert(0);
-                *count += 1;
+            {
+                let mut st = state.write().await;
+                let entry = st.mobile_views.entry(addr).or_default();
+                if entry.insert(session_id.clone()) {
+                    let count = st
+                        .session_view_counts
+                        .entry(session_id.clone())
+                        .or_insert(0);
+                    *count += 1;
+                }
             }
+
+            // Send the current screen so the client starts from a consistent state
+            send_screen_snapshot(state, tx, conn, &session_id).await?;
         }
         ClientMessage::Unsubscribe { session_id } => {
             tracing::debug!("Client unsubscribed from session: {}", session_id);
-            let mut st = state.write().await;
-            if let Some(entry) = st.mobile_views.get_mut(&addr) {
-                if entry.remove(&session_id) {
-                    if let Some(count) = st.session_view_counts.get_mut(&session_id) {
-                        if *count > 0 {
-                            *count -= 1;
-                        }
-                        if *count == 0 {
-                            st.session_view_counts.remove(&session_id);
-                            drop(st);
-                            restore_pty_size(state, &session_id).await;
-                            return Ok(());
+            let session = {
+                let mut st = state.write().await;
+                if let Some(entry) = st.mobile_views.get_mut(&addr) {
+                    if entry.remove(&session_id) {
+                        if let Some(count) = st.session_view_counts.get_mut(&session_id) {
+                            if *count > 0 {
+                                *count -= 1;
+                            }
+                            if *count == 0 {
+                                st.session_view_counts.remove(&session_id);
+                            }
                         }
                     }
                 }
+                st.session(&session_id)
+            };
+            if let Some(session) = session {
+                release_pty_size(&session, addr);
             }
         }
         ClientMessage::SendInput {
-            session_id, text, ..
+            session_id,
+            text,
+            submit,
+            ..
         } => {
-            let st = state.read().await;
-            if let Some(session) = st.sessions.get(&session_id) {
-                let _ = session.input_tx.send(text.into_bytes());
+            if let Some(session) = state.read().await.session(&session_id) {
+                // Typing makes this the active client (resize before the input lands)
+                session.lock().sizes.touch(addr);
+                session.apply_size();
+                if submit {
+                    session.type_and_submit(text.into_bytes());
+                } else {
+                    let _ = session.input_tx.send(text.into_bytes());
+                }
             }
         }
         ClientMessage::PtyResize {
@@ -683,111 +1296,397 @@ async fn process_client_msg(
             cols,
             rows,
         } => {
-            let st = state.read().await;
-            let is_restore = cols == 0 && rows == 0;
-            let has_viewers = st
-                .session_view_counts
-                .get(&session_id)
-                .copied()
-                .unwrap_or(0)
-                > 0;
-            if !is_restore && !has_viewers {
-                tracing::debug!(
-                    "Ignoring PTY resize for {} (no active mobile viewers)",
-                    session_id
-                );
-                return Ok(());
-            }
-            if let Some(session) = st.sessions.get(&session_id) {
-                let _ = session.resize_tx.send((cols, rows));
+            // (0, 0) withdraws this client's size
+            let is_restore = cols == 0 || rows == 0;
+            let session = {
+                let st = state.read().await;
+                let viewing = st
+                    .mobile_views
+                    .get(&addr)
+                    .is_some_and(|views| views.contains(&session_id));
+                if !is_restore && !viewing {
+                    tracing::debug!(
+                        "Ignoring PTY resize for {} (client is not viewing it)",
+                        session_id
+                    );
+                    return Ok(());
+                }
+                st.session(&session_id)
+            };
+            if let Some(session) = session {
+                if is_restore {
+                    session.lock().sizes.remove_viewer(addr);
+                } else {
+                    session.lock().sizes.set_viewer(addr, (cols, rows));
+                }
+                session.apply_size();
             }
         }
-        ClientMessage::Ping => {
-            tx.send(Message::Text(serde_json::to_string(&ServerMessage::Pong)?))
-                .await?;
-        }
-        ClientMessage::GetSessions => {
-            send_sessions_list(state, tx).await?;
-        }
-        ClientMessage::RenameSession {
+        ClientMessage::SetSizePolicy {
             session_id,
-            new_name,
+            policy,
+            cols,
+            rows,
         } => {
-            let renamed = {
-                let mut st = state.write().await;
-                if let Some(session) = st.sessions.get_mut(&session_id) {
-                    session.name = new_name.clone();
-                    true
-                } else {
-                    false
+            let fixed = cols.zip(rows).filter(|&(cols, rows)| cols > 0 && rows > 0);
+            let error = match (
+                SizePolicy::parse(&policy),
+                state.read().await.session(&session_id),
+            ) {
+                (_, None) => Some((
+                    "session_not_found",
+                    format!("Session {} not found", session_id),
+                )),
+                (None, _) => Some((
+                    "invalid_size_policy",
+                    format!("Unknown size policy '{}'", policy),
+                )),
+                (Some(SizePolicy::Fixed), _) if fixed.is_none() => Some((
+                    "invalid_size_policy",
+                    "The fixed size policy needs cols and rows".to_string(),
+                )),
+                (Some(policy), Some(session)) => {
+                    session.lock().sizes.set_policy(policy, fixed);
+                    session.apply_size();
+                    tracing::info!(
+                        "Session {} size policy set to {}",
+                        session_id,
+                        policy.as_str()
+                    );
+                    None
                 }
             };
 
-            if renamed {
-                // Send confirmation
-                let msg = ServerMessage::SessionRenamed {
-                    session_id: session_id.clone(),
-                    new_name: new_name.clone(),
-                };
-                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
-
-                // Broadcast updated sessions list to all clients
-                broadcast_sessions_update(state).await;
-
-                // Update persisted sessions file
-                persist_sessions_to_file(state).await;
-
-                tracing::info!("Session {} renamed to '{}'", session_id, new_name);
-            } else {
-                let msg = ServerMessage::Error {
-                    code: "session_not_found".to_string(),
-                    message: format!("Session {} not found", session_id),
-                };
-                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            match error {
+                Some((code, message)) => {
+                    let msg = ServerMessage::Error {
+                        code: code.to_string(),
+                        message,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                None => broadcast_sessions_update(state).await,
             }
         }
-        ClientMessage::RegisterPushToken {
-            token,
-            token_type,
-            platform,
-        } => {
-            let mut st = state.write().await;
-            // Remove existing token with same value to avoid duplicates
-            st.push_tokens.retain(|t| t.token != token);
-            st.push_tokens.push(PushToken {
-                token: token.clone(),
-                token_type: token_type.clone(),
-                platform: platform.clone(),
-            });
-            tracing::info!("Registered push token ({}/{})", token_type, platform);
-        }
-        ClientMessage::ToolApproval {
+        ClientMessage::AddTrigger {
             session_id,
-            response,
+            pattern,
+            name,
+            push,
+            cooldown_secs,
         } => {
-            let maybe_input = {
-                let mut st = state.write().await;
-                if let Some(session) = st.sessions.get_mut(&session_id) {
-                    let model = session
-                        .waiting_state
-                        .as_ref()
-                        .map(|w| w.approval_model)
-                        .unwrap_or_else(|| session.cli_tracker.current().default_approval_model());
-                    approval_input_for(model, response.as_str())
-                } else {
-                    None
-                }
+            let rule = TriggerRule {
+                name,
+                pattern,
+                cli: None,
+                push,
+                cooldown_secs,
             };
-
-            let mut cleared = false;
-            if let Some(input) = maybe_input {
-                let mut st = state.write().await;
-                if let Some(session) = st.sessions.get_mut(&session_id) {
-                    let _ = session.input_tx.send(input.as_bytes().to_vec());
-                    session.waiting_state = None;
-                    session.last_wait_hash = None;
-                    cleared = true;
-                }
+            let session = state.read().await.session(&session_id);
+            let msg = match session {
+                Some(session) => {
+                    let added = session.lock().triggers.add(rule, false);
+                    match added {
+                        Ok(id) => {
+                            broadcast_triggers(state, &session).await;
+                            ServerMessage::TriggerAdded { session_id, id }
+                        }
+                        Err(e) => ServerMessage::Error {
+                            code: "invalid_pattern".to_string(),
+                            message: e.to_string(),
+                        },
+                    }
+                }
+                None => ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::RemoveTrigger { session_id, id } => {
+            let session = state.read().await.session(&session_id);
+            let found = session
+                .as_ref()
+                .is_some_and(|session| session.lock().triggers.remove(id));
+            match session {
+                Some(session) if found => broadcast_triggers(state, &session).await,
+                _ => {
+                    let msg = ServerMessage::Error {
+                        code: "trigger_not_found".to_string(),
+                        message: format!("No trigger {} in session {}", id, session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+            }
+        }
+        ClientMessage::GetTriggers { session_id } => {
+            let msg = match state.read().await.session(&session_id) {
+                Some(session) => ServerMessage::Triggers {
+                    session_id,
+                    items: session.lock().triggers.items(),
+                },
+                None => ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::SendNotification {
+            title,
+            body,
+            session_id,
+            priority,
+        } => {
+            let priority = match priority.as_deref().unwrap_or("normal") {
+                "normal" => "normal",
+                "high" => "high",
+                other => {
+                    let msg = ServerMessage::Error {
+                        code: "invalid_priority".to_string(),
+                        message: format!("Unknown priority '{}' (use normal or high)", other),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                    return Ok(());
+                }
+            };
+            let session_name = match &session_id {
+                Some(session_id) => match state.read().await.session(session_id) {
+                    Some(session) => Some(session.lock().name.clone()),
+                    None => {
+                        let msg = ServerMessage::Error {
+                            code: "session_not_found".to_string(),
+                            message: format!("Session {} not found", session_id),
+                        };
+                        tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+
+            let msg = ServerMessage::CustomNotification {
+                session_id: session_id.clone(),
+                title: title.clone(),
+                body: body.clone(),
+                priority: priority.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            };
+            broadcast_event(state, &msg).await;
+
+            let push = PushNotification {
+                title: match session_name {
+                    Some(name) => format!("{} · {}", name, title),
+                    None => title,
+                },
+                body,
+                session_id,
+                kind: "custom",
+                priority,
+            };
+            let tokens = state.read().await.push_tokens.clone();
+            tokio::spawn(async move {
+                send_push_notifications(&tokens, &push).await;
+            });
+        }
+        ClientMessage::SetCommandNotify {
+            session_id,
+            after_secs,
+        } => {
+            match session_id {
+                Some(session_id) => match state.read().await.session(&session_id) {
+                    Some(session) => session.lock().notify_command_after_secs = Some(after_secs),
+                    None => {
+                        let msg = ServerMessage::Error {
+                            code: "session_not_found".to_string(),
+                            message: format!("Session {} not found", session_id),
+                        };
+                        tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                        return Ok(());
+                    }
+                },
+                None => state.write().await.notify_command_after_secs = after_secs,
+            }
+            broadcast_sessions_update(state).await;
+        }
+        ClientMessage::QueueInput {
+            session_id,
+            text,
+            deliver_on,
+        } => {
+            let deliver_on = deliver_on.as_deref().unwrap_or("awaiting_response");
+            let error = match (
+                WaitType::parse(deliver_on),
+                state.read().await.session(&session_id),
+            ) {
+                (_, None) => Some((
+                    "session_not_found",
+                    format!("Session {} not found", session_id),
+                )),
+                (None, _) => Some((
+                    "invalid_wait_type",
+                    format!("Unknown wait type '{}'", deliver_on),
+                )),
+                (Some(wait_type), Some(session)) => {
+                    // Already waiting for exactly this: deliver right away
+                    let ready = {
+                        let mut inner = session.lock();
+                        inner.input_queue.push(text, wait_type);
+                        let waiting = inner
+                            .waiting_state
+                            .as_ref()
+                            .is_some_and(|w| w.wait_type == wait_type);
+                        if waiting {
+                            inner.input_queue.take_ready(wait_type)
+                        } else {
+                            None
+                        }
+                    };
+                    match ready {
+                        Some(item) => deliver_queued_input(state, &session, item, wait_type).await,
+                        None => broadcast_input_queue(state, &session).await,
+                    }
+                    None
+                }
+            };
+
+            if let Some((code, message)) = error {
+                let msg = ServerMessage::Error {
+                    code: code.to_string(),
+                    message,
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            }
+        }
+        ClientMessage::GetInputQueue { session_id } => {
+            let msg = match state.read().await.session(&session_id) {
+                Some(session) => ServerMessage::InputQueue {
+                    session_id,
+                    items: session.lock().input_queue.items(),
+                },
+                None => ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::EditQueuedInput {
+            session_id,
+            id,
+            text,
+        } => {
+            let session = state.read().await.session(&session_id);
+            let found = session
+                .as_ref()
+                .is_some_and(|session| session.lock().input_queue.edit(id, text));
+            match session {
+                Some(session) if found => broadcast_input_queue(state, &session).await,
+                _ => {
+                    let msg = ServerMessage::Error {
+                        code: "queued_input_not_found".to_string(),
+                        message: format!("No queued input {} in session {}", id, session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+            }
+        }
+        ClientMessage::CancelQueuedInput { session_id, id } => {
+            let session = state.read().await.session(&session_id);
+            let found = session
+                .as_ref()
+                .is_some_and(|session| session.lock().input_queue.cancel(id));
+            match session {
+                Some(session) if found => broadcast_input_queue(state, &session).await,
+                _ => {
+                    let msg = ServerMessage::Error {
+                        code: "queued_input_not_found".to_string(),
+                        message: format!("No queued input {} in session {}", id, session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+            }
+        }
+        ClientMessage::Ping => {
+            tx.send(Message::Text(serde_json::to_string(&ServerMessage::Pong)?))
+                .await?;
+        }
+        ClientMessage::GetSessions => {
+            send_sessions_list(state, tx).await?;
+        }
+        ClientMessage::RenameSession {
+            session_id,
+            new_name,
+        } => {
+            let renamed = match state.read().await.session(&session_id) {
+                Some(session) => {
+                    session.lock().name = new_name.clone();
+                    true
+                }
+                None => false,
+            };
+
+            if renamed {
+                // Send confirmation
+                let msg = ServerMessage::SessionRenamed {
+                    session_id: session_id.clone(),
+                    new_name: new_name.clone(),
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+
+                // Broadcast updated sessions list to all clients
+                broadcast_sessions_update(state).await;
+
+                // Update persisted sessions file
+                persist_sessions_to_file(state).await;
+
+                tracing::info!("Session {} renamed to '{}'", session_id, new_name);
+            } else {
+                let msg = ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            }
+        }
+        ClientMessage::RegisterPushToken {
+            token,
+            token_type,
+            platform,
+        } => {
+            let mut st = state.write().await;
+            // Remove existing token with same value to avoid duplicates
+            st.push_tokens.retain(|t| t.token != token);
+            st.push_tokens.push(PushToken {
+                token: token.clone(),
+                token_type: token_type.clone(),
+                platform: platform.clone(),
+            });
+            tracing::info!("Registered push token ({}/{})", token_type, platform);
+        }
+        ClientMessage::ToolApproval {
+            session_id,
+            response,
+        } => {
+            let session = state.read().await.session(&session_id);
+            let maybe_input = session.as_ref().and_then(|session| {
+                let inner = session.lock();
+                let model = inner
+                    .waiting_state
+                    .as_ref()
+                    .map(|w| w.approval_model)
+                    .unwrap_or_else(|| inner.cli_tracker.current().default_approval_model());
+                approval_input_for(model, response.as_str())
+            });
+
+            let mut cleared = false;
+            if let (Some(input), Some(session)) = (maybe_input, session.as_ref()) {
+                let _ = session.input_tx.send(input.as_bytes().to_vec());
+                let mut inner = session.lock();
+                inner.waiting_state = None;
+                inner.last_wait_hash = None;
+                cleared = true;
             } else {
                 tracing::warn!(
                     "Tool approval ignored (no applicable approval model) for session {}",
@@ -803,19 +1702,21 @@ async fn process_client_msg(
             session_id,
             max_bytes,
         } => {
-            let (data, total_bytes) = {
-                let st = state.read().await;
-                if let Some(session) = st.sessions.get(&session_id) {
-                    let max = max_bytes.unwrap_or(session.scrollback_max_bytes);
-                    let total = session.scrollback.len();
-                    let skip = total.saturating_sub(max);
-                    // VecDeque doesn't support direct slicing, so collect the tail
-                    let bytes: Vec<u8> = session.scrollback.iter().skip(skip).copied().collect();
-                    (BASE64.encode(&bytes), total)
-                } else {
-                    (String::new(), 0)
+            // Only clone the shared chunks under the lock; encode afterwards
+            let session = state.read().await.session(&session_id);
+            let (chunks, total_bytes) = match session {
+                Some(session) => {
+                    let inner = session.lock();
+                    let max = max_bytes.unwrap_or(inner.scrollback.max_bytes());
+                    (inner.scrollback.tail(max), inner.scrollback.len())
                 }
+                None => (Vec::new(), 0),
             };
+            let mut encoder = base64::write::EncoderStringWriter::new(&BASE64);
+            for chunk in &chunks {
+                encoder.write_all(chunk)?;
+            }
+            let data = encoder.into_inner();
 
             let msg = ServerMessage::SessionHistory {
                 session_id,
@@ -824,10 +1725,366 @@ async fn process_client_msg(
             };
             tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
         }
+        ClientMessage::SearchSession {
+            pattern,
+            session_id,
+            since_secs,
+            case_insensitive,
+            max_results,
+        } => {
+            let regex = regex::RegexBuilder::new(&pattern)
+                .case_insensitive(case_insensitive)
+                .build();
+            let msg = match regex {
+                Ok(regex) => {
+                    let live: Vec<LiveLines> = {
+                        let st = state.read().await;
+                        st.sessions
+                            .values()
+                            .map(|s| {
+                                let inner = s.lock();
+                                LiveLines {
+                                    session_id: s.session_id.clone(),
+                                    name: inner.name.clone(),
+                                    first_line_number: inner.committed_lines,
+                                    offset: inner.output_offset,
+                                    lines: inner.live_lines.lines(),
+                                }
+                            })
+                            .collect()
+                    };
+                    let query = SearchQuery {
+                        regex,
+                        session: session_id,
+                        // Further back than chrono can represent: no lower bound
+                        since: since_secs.and_then(|secs| {
+                            let secs = i64::try_from(secs).ok()?;
+                            Utc::now().checked_sub_signed(chrono::TimeDelta::try_seconds(secs)?)
+                        }),
+                        max_results: max_results.unwrap_or(search::DEFAULT_MAX_RESULTS),
+                    };
+                    // Log files can be large; keep disk IO off the async workers
+                    let (matches, truncated) =
+                        tokio::task::spawn_blocking(move || search::run(&query, &live)).await?;
+                    ServerMessage::SearchResults {
+                        pattern,
+                        matches,
+                        truncated,
+                    }
+                }
+                Err(e) => ServerMessage::Error {
+                    code: "invalid_pattern".to_string(),
+                    message: e.to_string(),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetTranscript {
+            session_id,
+            last_lines,
+        } => {
+            let rendered = state.read().await.session(&session_id).map(|session| {
+                let inner = session.lock();
+                (inner.scrollback.tail(usize::MAX), inner.screen.size().1)
+            });
+
+            let msg = match rendered {
+                Some((chunks, rows)) => {
+                    let scrollback = scrollback::concat(&chunks);
+                    let lines = transcript::render_lines(&scrollback, rows);
+                    let total_lines = lines.len();
+                    ServerMessage::Transcript {
+                        session_id,
+                        lines: match last_lines {
+                            Some(n) => transcript::tail_lines(lines, n),
+                            None => lines,
+                        },
+                        total_lines,
+                    }
+                }
+                None => ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::ExportSession { session_id } => {
+            let msg = match recording::open_recording(&session_id).await {
+                Ok((mut file, len)) => {
+                    // A live recording may grow while it is read; export what
+                    // was there when it was opened
+                    let total_bytes = len as usize;
+                    let mut chunk = vec![0; recording::EXPORT_CHUNK_BYTES];
+                    let mut offset = 0;
+                    loop {
+                        let want = chunk.len().min(total_bytes - offset);
+                        if let Err(e) = file.read_exact(&mut chunk[..want]).await {
+                            let msg = ServerMessage::Error {
+                                code: "export_failed".to_string(),
+                                message: e.to_string(),
+                            };
+                            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                            return Ok(());
+                        }
+                        let done = offset + want == total_bytes;
+                        let msg = ServerMessage::SessionExport {
+                            session_id: session_id.clone(),
+                            format: "asciicast-v2".to_string(),
+                            filename: format!("{}.cast", session_id),
+                            data: BASE64.encode(&chunk[..want]),
+                            offset,
+                            total_bytes,
+                            done,
+                        };
+                        tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                        offset += want;
+                        if done {
+                            break;
+                        }
+                    }
+                    return Ok(());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => ServerMessage::Error {
+                    code: "recording_not_found".to_string(),
+                    message: format!("No recording for session {}", session_id),
+                },
+                Err(e) => ServerMessage::Error {
+                    code: "export_failed".to_string(),
+                    message: e.to_string(),
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetDiagnostics => {
+            let msg = {
+                let st = state.read().await;
+                ServerMessage::Diagnostics {
+                    clients: st
+                        .mobile_clients
+                        .iter()
+                        .map(|(addr, client)| ClientDiagnostics {
+                            address: addr.to_string(),
+                            connected_at: client.stats.connected_at.to_rfc3339(),
+                            queued_messages: client.queued_messages(),
+                            queued_output: client.stats.queued_output.load(Ordering::Relaxed),
+                            pending_batch_bytes: client
+                                .stats
+                                .pending_batch_bytes
+                                .load(Ordering::Relaxed),
+                            overflows: client.stats.overflows.load(Ordering::Relaxed),
+                        })
+                        .collect(),
+                    sessions: st.sessions.len(),
+                    scrollback_bytes: st.scrollback_budget.used(),
+                    scrollback_max_bytes: st.scrollback_budget.max_bytes,
+                    overflow_policy: st.overflow_policy.as_str().to_string(),
+                }
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::SetBatching {
+            max_delay_ms,
+            max_bytes,
+        } => {
+            let current = conn.batch_config;
+            conn.set_batch_config(BatchConfig::new(
+                max_delay_ms.unwrap_or(current.max_delay.as_millis() as u64),
+                max_bytes.unwrap_or(current.max_bytes),
+            ));
+            tracing::debug!("Client {} batching: {:?}", addr, conn.batch_config);
+        }
     }
     Ok(())
 }
 
+/// Send a session's current screen and skip output it already covers
+async fn send_screen_snapshot(
+    state: &SharedState,
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    conn: &mut ClientConnection,
+    session_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let snapshot = state.read().await.session(session_id).map(|s| {
+        let inner = s.lock();
+        (inner.screen.snapshot(), inner.output_offset)
+    });
+    let Some((snap, offset)) = snapshot else {
+        return Ok(());
+    };
+
+    conn.snapshot_offsets.insert(session_id.to_string(), offset);
+    let msg = ServerMessage::ScreenSnapshot {
+        session_id: session_id.to_string(),
+        cols: snap.cols,
+        rows: snap.rows,
+        cursor_row: snap.cursor_row,
+        cursor_col: snap.cursor_col,
+        cursor_visible: snap.cursor_visible,
+        alternate_screen: snap.alternate_screen,
+        application_cursor: snap.application_cursor,
+        application_keypad: snap.application_keypad,
+        bracketed_paste: snap.bracketed_paste,
+        title: snap.title,
+        data: BASE64.encode(&snap.data),
+    };
+    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+    Ok(())
+}
+
+/// Apply the overflow policy to a client that fell behind. With `Resync`
+/// the backlog is dropped (queued state updates included, as they'd arrive
+/// after the fresh state) and the client gets fresh state: session list,
+/// waiting states and a screen snapshot of every session it has output for,
+/// then the events that were queued or held. An `Err` means the connection
+/// should be closed.
+async fn handle_overflow(
+    state: &SharedState,
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    client_rx: &mut mpsc::Receiver<Queued>,
+    addr: SocketAddr,
+    conn: &mut ClientConnection,
+    stats: &ClientStats,
+    policy: OverflowPolicy,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    stats.overflows.fetch_add(1, Ordering::Relaxed);
+    if policy == OverflowPolicy::Disconnect {
+        tracing::info!("Disconnecting slow client {}", addr);
+        return Err("client queue overflowed".into());
+    }
+
+    tracing::info!("Resyncing slow client {}", addr);
+    let mut events = Vec::new();
+    while let Ok(queued) = client_rx.try_recv() {
+        if queued.event {
+            events.push(queued.msg);
+        }
+    }
+    let mut session_ids = conn.discard_pending();
+    if let Some(views) = state.read().await.mobile_views.get(&addr) {
+        session_ids.extend(views.iter().cloned());
+    }
+    session_ids.sort();
+    session_ids.dedup();
+
+    send_sessions_list(state, tx).await?;
+    send_waiting_states(state, tx).await?;
+    for session_id in session_ids {
+        send_screen_snapshot(state, tx, conn, &session_id).await?;
+    }
+    // Held events came after the queued ones
+    events.extend(stats.held_events().drain(..));
+    for msg in events {
+        tx.send(msg).await?;
+    }
+    Ok(())
+}
+
+/// Send PTY output to a mobile client
+async fn send_pty_bytes(
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    session_id: String,
+    data: &[u8],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let msg = ServerMessage::PtyBytes {
+        session_id,
+        data: BASE64.encode(data),
+    };
+    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+    Ok(())
+}
+
+/// Send flushed output batches to a mobile client
+async fn send_batches(
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    batches: Vec<(String, Vec<u8>)>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for (session_id, data) in batches {
+        send_pty_bytes(tx, session_id, &data).await?;
+    }
+    Ok(())
+}
+
+/// Update scrollback and the emulated screen, then fan the chunk out to clients.
+/// Only the session's own lock is taken unless the scrollback budget is exceeded.
+async fn ingest_output(
+    state: &SharedState,
+    session: &PtySession,
+    pty_broadcast: &broadcast::Sender<PtyChunk>,
+    scrollback_budget: &ScrollbackBudget,
+    bytes: Bytes,
+    output_log: &mut Option<OutputLog>,
+) {
+    let (end_offset, committed, first_line_number) = {
+        let mut inner = session.lock();
+        // Accumulate scrollback for session history (linked terminals);
+        // the chunk is shared with the broadcast below, not copied
+        let before = inner.scrollback.len();
+        inner.scrollback.push(bytes.clone());
+        scrollback_budget.update(before, inner.scrollback.len());
+        inner.screen.process(&bytes);
+        inner.output_offset += bytes.len() as u64;
+
+        inner.live_utf8.extend_from_slice(&bytes);
+        let text = recording::take_complete_utf8(&mut inner.live_utf8);
+        inner.live_lines.feed(&text);
+        let committed = inner.live_lines.take_committed();
+        let first_line_number = inner.committed_lines;
+        inner.committed_lines += committed.len();
+        (inner.output_offset, committed, first_line_number)
+    };
+
+    if scrollback_budget.excess() > 0 {
+        state.read().await.enforce_scrollback_budget();
+    }
+
+    write_output_log(output_log, &committed, first_line_number, end_offset);
+
+    // Broadcast after the screen is updated so snapshot offsets stay consistent
+    let _ = pty_broadcast.send(PtyChunk {
+        session_id: session.session_id.clone(),
+        data: bytes,
+        end_offset,
+    });
+}
+
+/// Append committed lines to the session's output log, dropping the log on write errors
+fn write_output_log(
+    output_log: &mut Option<OutputLog>,
+    lines: &[String],
+    first_line_number: usize,
+    offset: u64,
+) {
+    if let Some(log) = output_log.as_mut() {
+        if let Err(e) = log.append(lines, first_line_number, offset) {
+            tracing::warn!("Output log write failed: {}", e);
+            *output_log = None;
+        }
+    }
+}
+
+/// Append PTY output to the session recording, stopping the recording on write errors
+fn record_output(recorder: &mut Option<CastRecorder>, data: &[u8]) {
+    if let Some(r) = recorder.as_mut() {
+        if let Err(e) = r.write_output(data) {
+            tracing::warn!("Recording write failed ({}): {}", r.path().display(), e);
+            *recorder = None;
+        }
+    }
+}
+
 /// Send sessions list to a client
 async fn send_sessions_list(
     state: &SharedState,
@@ -837,21 +2094,9 @@ async fn send_sessions_list(
     >,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let st = state.read().await;
-    let port = st.port;
-    let items: Vec<SessionListItem> = st
-        .sessions
-        .values()
-        .map(|s| SessionListItem {
-            session_id: s.session_id.clone(),
-            name: s.name.clone(),
-            command: s.command.clone(),
-            project_path: s.project_path.clone(),
-            ws_port: port,
-            started_at: s.started_at.to_rfc3339(),
-            cli_type: s.cli_tracker.current().as_str().to_string(),
-        })
-        .collect();
-    let msg = ServerMessage::Sessions { sessions: items };
+    let msg = ServerMessage::Sessions {
+        sessions: st.session_list_items(),
+    };
     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
     Ok(())
 }
@@ -859,24 +2104,12 @@ async fn send_sessions_list(
 /// Broadcast sessions update to all mobile clients
 async fn broadcast_sessions_update(state: &SharedState) {
     let st = state.read().await;
-    let port = st.port;
-    let items: Vec<SessionListItem> = st
-        .sessions
-        .values()
-        .map(|s| SessionListItem {
-            session_id: s.session_id.clone(),
-            name: s.name.clone(),
-            command: s.command.clone(),
-            project_path: s.project_path.clone(),
-            ws_port: port,
-            started_at: s.started_at.to_rfc3339(),
-            cli_type: s.cli_tracker.current().as_str().to_string(),
-        })
-        .collect();
-    let msg = ServerMessage::Sessions { sessions: items };
+    let msg = ServerMessage::Sessions {
+        sessions: st.session_list_items(),
+    };
     if let Ok(msg_str) = serde_json::to_string(&msg) {
         for client in st.mobile_clients.values() {
-            let _ = client.send(Message::Text(msg_str.clone()));
+            client.send(Message::Text(msg_str.clone()));
         }
     }
 }
@@ -888,15 +2121,23 @@ async fn persist_sessions_to_file(state: &SharedState) {
     let sessions: Vec<SessionInfo> = st
         .sessions
         .values()
-        .map(|s| SessionInfo {
-            session_id: s.session_id.clone(),
-            name: s.name.clone(),
-            command: s.command.clone(),
-            args: vec![],
-            project_path: s.project_path.clone(),
-            ws_port: port,
-            pid: std::process::id(), // daemon PID since we manage all sessions
-            started_at: s.started_at,
+        .map(|s| {
+            let inner = s.lock();
+            let workdir = inner.workdir.as_ref();
+            let git = workdir.and_then(|w| w.git.as_ref());
+            SessionInfo {
+                session_id: s.session_id.clone(),
+                name: inner.name.clone(),
+                command: s.command.clone(),
+                args: vec![],
+                project_path: s.project_path.clone(),
+                ws_port: port,
+                pid: std::process::id(), // daemon PID since we manage all sessions
+                started_at: s.started_at,
+                cwd: workdir.map(|w| w.path.clone()),
+                git_branch: git.map(|g| g.branch.clone()),
+                git_dirty: git.map(|g| g.dirty),
+            }
         })
         .collect();
     if let Err(e) = session::save_sessions(&sessions) {
@@ -904,6 +2145,157 @@ async fn persist_sessions_to_file(state: &SharedState) {
     }
 }
 
+async fn handle_shell_event(state: &SharedState, session: &Arc<PtySession>, event: ShellEvent) {
+    match event {
+        ShellEvent::Cwd(path) => report_cwd(state, session, path),
+        ShellEvent::CommandStarted { command } => {
+            let started_at = Utc::now();
+            session.lock().running_command = Some(RunningCommand {
+                command: command.clone(),
+                started_at,
+            });
+            let msg = ServerMessage::CommandStarted {
+                session_id: session.session_id.clone(),
+                command,
+                timestamp: started_at.to_rfc3339(),
+            };
+            broadcast_event(state, &msg).await;
+        }
+        ShellEvent::CommandFinished { exit_code } => {
+            finish_command(state, session, exit_code).await
+        }
+        // A new prompt also ends a command whose shell didn't report it
+        ShellEvent::PromptStart => finish_command(state, session, None).await,
+    }
+}
+
+/// Tell clients the session's running command finished and how long it
+/// took; commands over the notify threshold also send a push notification
+async fn finish_command(state: &SharedState, session: &PtySession, exit_code: Option<i32>) {
+    let (running, threshold, name) = {
+        let mut inner = session.lock();
+        let Some(running) = inner.running_command.take() else {
+            return;
+        };
+        (running, inner.notify_command_after_secs, inner.name.clone())
+    };
+    let (threshold, tokens) = {
+        let st = state.read().await;
+        (
+            threshold.unwrap_or(st.notify_command_after_secs),
+            st.push_tokens.clone(),
+        )
+    };
+    let finished_at = Utc::now();
+    let duration_ms = finished_at
+        .signed_duration_since(running.started_at)
+        .num_milliseconds()
+        .max(0) as u64;
+    let notify = threshold > 0 && duration_ms >= threshold.saturating_mul(1000);
+
+    if notify {
+        let (title, body) = build_command_notification_text(
+            &name,
+            running.command.as_deref(),
+            exit_code,
+            duration_ms,
+        );
+        let push = PushNotification {
+            title,
+            body,
+            session_id: Some(session.session_id.clone()),
+            kind: "command_finished",
+            priority: "high",
+        };
+        tokio::spawn(async move {
+            send_push_notifications(&tokens, &push).await;
+        });
+    }
+
+    let msg = ServerMessage::CommandFinished {
+        session_id: session.session_id.clone(),
+        command: running.command,
+        exit_code,
+        duration_ms,
+        timestamp: finished_at.to_rfc3339(),
+        notify,
+    };
+    broadcast_event(state, &msg).await;
+}
+
+/// Broadcast a message to all mobile clients
+async fn broadcast_message(state: &SharedState, msg: &ServerMessage) {
+    let st = state.read().await;
+    if let Ok(msg_str) = serde_json::to_string(msg) {
+        for client in st.mobile_clients.values() {
+            client.send(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
+/// Broadcast an event that must reach every client, even one that is
+/// being resynced (see `MobileClient::send_event`)
+async fn broadcast_event(state: &SharedState, msg: &ServerMessage) {
+    let st = state.read().await;
+    if let Ok(msg_str) = serde_json::to_string(msg) {
+        for client in st.mobile_clients.values() {
+            client.send_event(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
+/// Record a session's working directory and re-check its git state in the
+/// background; clients hear about it if either changed
+fn report_cwd(state: &SharedState, session: &Arc<PtySession>, path: String) {
+    session.lock().cwd = Some(path.clone());
+    let state = state.clone();
+    let session = session.clone();
+    tokio::spawn(async move {
+        let workdir = WorkDir::inspect(path).await;
+        let changed = {
+            let mut inner = session.lock();
+            // Skip results overtaken by a newer report
+            let current = inner.cwd.as_deref() == Some(workdir.path.as_str());
+            if current && inner.workdir.as_ref() != Some(&workdir) {
+                inner.workdir = Some(workdir);
+                true
+            } else {
+                false
+            }
+        };
+        if changed {
+            broadcast_session_info(&state, &session).await;
+            broadcast_sessions_update(&state).await;
+            persist_sessions_to_file(&state).await;
+        }
+    });
+}
+
+/// Broadcast session_info (name, current directory, git state) to all mobile clients
+async fn broadcast_session_info(state: &SharedState, session: &PtySession) {
+    let msg = {
+        let inner = session.lock();
+        let workdir = inner.workdir.as_ref();
+        let git = workdir.and_then(|w| w.git.as_ref());
+        ServerMessage::SessionInfo {
+            session_id: session.session_id.clone(),
+            name: inner.name.clone(),
+            command: session.command.clone(),
+            project_path: session.project_path.clone(),
+            started_at: session.started_at.to_rfc3339(),
+            cwd: workdir.map(|w| w.path.clone()),
+            git_branch: git.map(|g| g.branch.clone()),
+            git_dirty: git.map(|g| g.dirty),
+        }
+    };
+    let st = state.read().await;
+    if let Ok(msg_str) = serde_json::to_string(&msg) {
+        for client in st.mobile_clients.values() {
+            client.send(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
 /// Broadcast waiting_for_input to all mobile clients
 async fn broadcast_waiting_for_input(state: &SharedState, session_id: &str) {
     let st = state.read().await;
@@ -911,21 +2303,23 @@ async fn broadcast_waiting_for_input(state: &SharedState, session_id: &str) {
         Some(s) => s,
         None => return,
     };
-    let waiting = match session.waiting_state.as_ref() {
-        Some(w) => w,
-        None => return,
-    };
-
-    let msg = ServerMessage::WaitingForInput {
-        session_id: session_id.to_string(),
-        timestamp: waiting.timestamp.to_rfc3339(),
-        prompt_content: waiting.prompt_content.clone(),
-        wait_type: waiting.wait_type.as_str().to_string(),
-        cli_type: session.cli_tracker.current().as_str().to_string(),
+    let msg = {
+        let inner = session.lock();
+        let waiting = match inner.waiting_state.as_ref() {
+            Some(w) => w,
+            None => return,
+        };
+        ServerMessage::WaitingForInput {
+            session_id: session_id.to_string(),
+            timestamp: waiting.timestamp.to_rfc3339(),
+            prompt_content: waiting.prompt_content.clone(),
+            wait_type: waiting.wait_type.as_str().to_string(),
+            cli_type: inner.cli_tracker.current().as_str().to_string(),
+        }
     };
     if let Ok(msg_str) = serde_json::to_string(&msg) {
         for client in st.mobile_clients.values() {
-            let _ = client.send(Message::Text(msg_str.clone()));
+            client.send(Message::Text(msg_str.clone()));
         }
     }
 }
@@ -939,11 +2333,109 @@ async fn broadcast_waiting_cleared(state: &SharedState, session_id: &str) {
     };
     if let Ok(msg_str) = serde_json::to_string(&msg) {
         for client in st.mobile_clients.values() {
-            let _ = client.send(Message::Text(msg_str.clone()));
+            client.send(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
+/// Broadcast the size a session's PTY now has to all mobile clients
+async fn broadcast_pty_resized(state: &SharedState, session_id: &str, cols: u16, rows: u16) {
+    let st = state.read().await;
+    let msg = ServerMessage::PtyResized {
+        session_id: session_id.to_string(),
+        cols,
+        rows,
+    };
+    if let Ok(msg_str) = serde_json::to_string(&msg) {
+        for client in st.mobile_clients.values() {
+            client.send(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
+/// Broadcast a session's triggers to all mobile clients
+async fn broadcast_triggers(state: &SharedState, session: &PtySession) {
+    let msg = ServerMessage::Triggers {
+        session_id: session.session_id.clone(),
+        items: session.lock().triggers.items(),
+    };
+    broadcast_message(state, &msg).await;
+}
+
+/// Tell clients a trigger fired, and push it if the trigger asks for that
+async fn trigger_matched(state: &SharedState, session: &PtySession, hit: TriggerHit) {
+    tracing::info!(
+        "Trigger /{}/ matched in session {}: {}",
+        hit.pattern,
+        session.session_id,
+        hit.line
+    );
+    let msg = ServerMessage::TriggerMatched {
+        session_id: session.session_id.clone(),
+        trigger_id: hit.id,
+        name: hit.name.clone(),
+        pattern: hit.pattern.clone(),
+        matched: hit.matched.clone(),
+        line: hit.line.clone(),
+        timestamp: Utc::now().to_rfc3339(),
+    };
+    broadcast_event(state, &msg).await;
+
+    if hit.push {
+        let push = PushNotification {
+            title: format!("{} · {}", session.lock().name, hit.label()),
+            body: hit.line,
+            session_id: Some(session.session_id.clone()),
+            kind: "trigger",
+            priority: "high",
+        };
+        let tokens = state.read().await.push_tokens.clone();
+        tokio::spawn(async move {
+            send_push_notifications(&tokens, &push).await;
+        });
+    }
+}
+
+/// Broadcast a session's input queue to all mobile clients
+async fn broadcast_input_queue(state: &SharedState, session: &PtySession) {
+    let msg = ServerMessage::InputQueue {
+        session_id: session.session_id.clone(),
+        items: session.lock().input_queue.items(),
+    };
+    let st = state.read().await;
+    if let Ok(msg_str) = serde_json::to_string(&msg) {
+        for client in st.mobile_clients.values() {
+            client.send(Message::Text(msg_str.clone()));
         }
     }
 }
 
+/// Type a queued input into its session, then tell mobile clients it went
+/// out and what is still queued
+async fn deliver_queued_input(
+    state: &SharedState,
+    session: &PtySession,
+    item: QueuedInput,
+    wait_type: WaitType,
+) {
+    session.type_and_submit(item.text.clone().into_bytes());
+    tracing::info!(
+        "Delivered queued input {} to session {} ({})",
+        item.id,
+        session.session_id,
+        wait_type.as_str()
+    );
+
+    let msg = ServerMessage::QueuedInputDelivered {
+        session_id: session.session_id.clone(),
+        id: item.id,
+        text: item.text,
+        wait_type: wait_type.as_str().to_string(),
+    };
+    broadcast_event(state, &msg).await;
+    broadcast_input_queue(state, session).await;
+}
+
 /// Send current waiting states to a newly connected mobile client.
 async fn send_waiting_states(
     state: &SharedState,
@@ -952,18 +2444,25 @@ async fn send_waiting_states(
         Message,
     >,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let st = state.read().await;
-    for session in st.sessions.values() {
-        if let Some(waiting) = &session.waiting_state {
-            let msg = ServerMessage::WaitingForInput {
-                session_id: session.session_id.clone(),
-                timestamp: waiting.timestamp.to_rfc3339(),
-                prompt_content: waiting.prompt_content.clone(),
-                wait_type: waiting.wait_type.as_str().to_string(),
-                cli_type: session.cli_tracker.current().as_str().to_string(),
-            };
-            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
-        }
+    let messages: Vec<ServerMessage> = {
+        let st = state.read().await;
+        st.sessions
+            .values()
+            .filter_map(|session| {
+                let inner = session.lock();
+                let waiting = inner.waiting_state.as_ref()?;
+                Some(ServerMessage::WaitingForInput {
+                    session_id: session.session_id.clone(),
+                    timestamp: waiting.timestamp.to_rfc3339(),
+                    prompt_content: waiting.prompt_content.clone(),
+                    wait_type: waiting.wait_type.as_str().to_string(),
+                    cli_type: inner.cli_tracker.current().as_str().to_string(),
+                })
+            })
+            .collect()
+    };
+    for msg in messages {
+        tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
     }
     Ok(())
 }
@@ -1034,14 +2533,44 @@ fn build_notification_text(
     (title_with_session, body)
 }
 
+fn build_command_notification_text(
+    session_name: &str,
+    command: Option<&str>,
+    exit_code: Option<i32>,
+    duration_ms: u64,
+) -> (String, String) {
+    let title = match exit_code {
+        Some(code) if code != 0 => "Command Failed",
+        _ => "Command Finished",
+    };
+    let command = command
+        .map(|c| c.chars().take(80).collect::<String>())
+        .unwrap_or_else(|| "Command".to_string());
+    let elapsed = format_elapsed(duration_ms / 1000);
+    let body = match exit_code {
+        Some(code) if code != 0 => format!("{} exited with {} after {}", command, code, elapsed),
+        _ => format!("{} finished after {}", command, elapsed),
+    };
+    (format!("{} · {}", session_name, title), body)
+}
+
+/// "45s", "3m 12s", "2h 5m"
+fn format_elapsed(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
 async fn cleanup_mobile_views(state: &SharedState, addr: SocketAddr) {
-    let sessions_to_restore = {
+    let viewed_sessions = {
         let mut st = state.write().await;
         let sessions = match st.mobile_views.remove(&addr) {
             Some(s) => s,
             None => return,
         };
-        let mut restore = Vec::new();
+        let mut viewed = Vec::new();
         for session_id in sessions {
             if let Some(count) = st.session_view_counts.get_mut(&session_id) {
                 if *count > 0 {
@@ -1049,27 +2578,37 @@ async fn cleanup_mobile_views(state: &SharedState, addr: SocketAddr) {
                 }
                 if *count == 0 {
                     st.session_view_counts.remove(&session_id);
-                    restore.push(session_id);
                 }
             }
+            viewed.extend(st.session(&session_id));
         }
-        restore
+        viewed
     };
 
-    for session_id in sessions_to_restore {
-        restore_pty_size(state, &session_id).await;
+    for session in viewed_sessions {
+        release_pty_size(&session, addr);
     }
 }
 
-async fn restore_pty_size(state: &SharedState, session_id: &str) {
-    let st = state.read().await;
-    if let Some(session) = st.sessions.get(session_id) {
-        let _ = session.resize_tx.send((0, 0));
-    }
+/// Drop a detached viewer's size from the session's size arbitration
+fn release_pty_size(session: &PtySession, addr: SocketAddr) {
+    session.lock().sizes.remove_viewer(addr);
+    session.apply_size();
+}
+
+/// A push notification for every registered device
+struct PushNotification {
+    title: String,
+    body: String,
+    session_id: Option<String>,
+    /// `data.type` for the app: "waiting_for_input" | "command_finished" | "custom" | "trigger"
+    kind: &'static str,
+    /// Expo priority: "normal" | "high"
+    priority: &'static str,
 }
 
 /// Send push notifications to all registered tokens
-async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str, session_id: &str) {
+async fn send_push_notifications(tokens: &[PushToken], push: &PushNotification) {
     if tokens.is_empty() {
         return;
     }
@@ -1081,15 +2620,15 @@ async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str,
         .map(|t| {
             serde_json::json!({
                 "to": t.token,
-                "title": title,
-                "body": body,
+                "title": push.title,
+                "body": push.body,
                 "data": {
-                    "sessionId": session_id,
-                    "session_id": session_id,
-                    "type": "waiting_for_input"
+                    "sessionId": push.session_id,
+                    "session_id": push.session_id,
+                    "type": push.kind
                 },
                 "sound": "default",
-                "priority": "high"
+                "priority": push.priority
             })
         })
         .collect();
@@ -1118,3 +2657,125 @@ async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio_tungstenite::connect_async;
+
+    /// Many noisy sessions streaming through a real daemon listener at once.
+    /// Run with `cargo test --release load_test -- --ignored --nocapture`.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[ignore]
+    async fn load_test_many_noisy_sessions() {
+        const SESSIONS: usize = 32;
+        const CHUNKS: usize = 500;
+        const CHUNK_BYTES: usize = 4096;
+
+        // Keep output logs and the sessions file out of the real config dir
+        let home = std::env::temp_dir().join(format!("mobilecli-load-{}", std::process::id()));
+        std::env::set_var("HOME", &home);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state: SharedState = Arc::new(RwLock::new(DaemonState::new(port)));
+        {
+            let state = state.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, addr)) = listener.accept().await {
+                    tokio::spawn(handle_connection(stream, addr, state.clone()));
+                }
+            });
+        }
+        let url = format!("ws://127.0.0.1:{}", port);
+
+        // A mobile client receiving every session's output
+        let (mut viewer, _) = connect_async(&url).await.unwrap();
+        let hello = ClientMessage::Hello {
+            auth_token: None,
+            client_version: "load-test".to_string(),
+        };
+        viewer
+            .send(Message::Text(serde_json::to_string(&hello).unwrap()))
+            .await
+            .unwrap();
+        let viewer_bytes = Arc::new(AtomicUsize::new(0));
+        {
+            let viewer_bytes = viewer_bytes.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(Message::Text(text))) = viewer.next().await {
+                    if let Ok(ServerMessage::PtyBytes { data, .. }) = serde_json::from_str(&text) {
+                        let len = BASE64.decode(data).map(|d| d.len()).unwrap_or(0);
+                        viewer_bytes.fetch_add(len, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+
+        let line = format!("{}\r\n", "x".repeat(CHUNK_BYTES - 2));
+        let output = serde_json::json!({
+            "type": "pty_output",
+            "data": BASE64.encode(line),
+        })
+        .to_string();
+
+        let start = Instant::now();
+        let wrappers: Vec<_> = (0..SESSIONS)
+            .map(|i| {
+                let url = url.clone();
+                let output = output.clone();
+                tokio::spawn(async move {
+                    let (mut ws, _) = connect_async(&url).await.unwrap();
+                    let register = serde_json::json!({
+                        "type": "register_pty",
+                        "session_id": format!("load-{}", i),
+                        "name": format!("load {}", i),
+                        "command": "load",
+                        "cols": 120,
+                        "rows": 40,
+                    });
+                    ws.send(Message::Text(register.to_string())).await.unwrap();
+                    ws.next().await.unwrap().unwrap();
+                    for _ in 0..CHUNKS {
+                        ws.send(Message::Text(output.clone())).await.unwrap();
+                    }
+                    // Closing the socket would end the session; hand it back
+                    ws
+                })
+            })
+            .collect();
+        let mut open_sockets = Vec::new();
+        for wrapper in wrappers {
+            open_sockets.push(wrapper.await.unwrap());
+        }
+
+        let expected = (SESSIONS * CHUNKS * CHUNK_BYTES) as u64;
+        let ingested = loop {
+            let ingested: u64 = {
+                let st = state.read().await;
+                st.sessions.values().map(|s| s.lock().output_offset).sum()
+            };
+            if ingested >= expected || start.elapsed() > Duration::from_secs(120) {
+                break ingested;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        let elapsed = start.elapsed();
+
+        let mb = expected as f64 / (1024.0 * 1024.0);
+        println!(
+            "{} sessions x {} chunks: {:.0} MB in {:.2?} ({:.1} MB/s), viewer received {:.0} MB",
+            SESSIONS,
+            CHUNKS,
+            mb,
+            elapsed,
+            mb / elapsed.as_secs_f64(),
+            viewer_bytes.load(Ordering::Relaxed) as f64 / (1024.0 * 1024.0),
+        );
+
+        drop(open_sockets);
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(ingested, expected);
+    }
+}
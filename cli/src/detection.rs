@@ -68,6 +68,16 @@ impl WaitType {
             WaitType::AwaitingResponse => "awaiting_response",
         }
     }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tool_approval" => Some(WaitType::ToolApproval),
+            "plan_approval" => Some(WaitType::PlanApproval),
+            "clarifying_question" => Some(WaitType::ClarifyingQuestion),
+            "awaiting_response" => Some(WaitType::AwaitingResponse),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -125,6 +135,25 @@ impl CliTracker {
         }
     }
 
+    /// The PTY's foreground process changed. A known agent CLI, shell or
+    /// editor outranks everything else: earlier evidence is discarded, and a
+    /// shell or editor means the session is a plain terminal again. Other
+    /// programs (a wrapper script, `cli.js` under node) say nothing, so the
+    /// evidence so far stands.
+    pub fn update_from_foreground(&mut self, program: &str) {
+        let cli = match cli_from_command(program) {
+            Some(cli) => cli,
+            None if TERMINAL_PROGRAMS.contains(&program.to_lowercase().as_str()) => {
+                CliType::Terminal
+            }
+            None => return,
+        };
+        for score in self.scores.values_mut() {
+            *score = 0;
+        }
+        self.apply_signal(cli, 8);
+    }
+
     pub fn update_from_output(&mut self, text: &str) {
         if let Some(cli) = cli_from_output(text) {
             // Moderate signal: banner/output patterns
@@ -154,6 +183,60 @@ impl CliTracker {
     }
 }
 
+/// Shells and editors: in the foreground, the session is a plain terminal
+const TERMINAL_PROGRAMS: &[&str] = &[
+    "sh",
+    "bash",
+    "zsh",
+    "fish",
+    "dash",
+    "ksh",
+    "tcsh",
+    "csh",
+    "nu",
+    "pwsh",
+    "powershell",
+    "cmd",
+    "vi",
+    "vim",
+    "nvim",
+    "nano",
+    "emacs",
+    "hx",
+    "helix",
+    "micro",
+    "kak",
+    "less",
+    "more",
+    "man",
+];
+
+/// Interpreters whose script argument names the actual program
+const INTERPRETERS: &[&str] = &[
+    "node", "nodejs", "bun", "deno", "python", "python3", "ruby", "perl",
+];
+
+/// Program name of a process from its argv: the basename of argv[0], or of
+/// the script for interpreters (`node /usr/bin/claude` is `claude`). Login
+/// shells' leading `-` is dropped.
+pub fn program_name(argv: &[String]) -> Option<String> {
+    let basename = |arg: &str| {
+        std::path::Path::new(arg)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or(arg)
+            .to_string()
+    };
+    let program = basename(argv.first()?.trim_start_matches('-'));
+    if !INTERPRETERS.contains(&program.as_str()) {
+        return Some(program);
+    }
+    let script = argv[1..]
+        .iter()
+        .find(|arg| !arg.starts_with('-') && arg.as_str() != "run");
+    Some(script.map(|s| basename(s)).unwrap_or(program))
+}
+
 fn cli_from_command(command: &str) -> Option<CliType> {
     let base = std::path::Path::new(command)
         .file_name()
@@ -381,3 +464,51 @@ pub fn detect_wait_event(input: &str, cli: CliType) -> Option<WaitEvent> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_program_name() {
+        assert_eq!(program_name(&argv(&["-zsh"])), Some("zsh".into()));
+        assert_eq!(
+            program_name(&argv(&["/usr/bin/vim", "x"])),
+            Some("vim".into())
+        );
+        assert_eq!(
+            program_name(&argv(&["node", "--no-warnings", "/usr/lib/claude/cli.js"])),
+            Some("cli.js".into())
+        );
+        assert_eq!(
+            program_name(&argv(&["node", "/usr/local/bin/claude"])),
+            Some("claude".into())
+        );
+        assert_eq!(program_name(&argv(&["python3"])), Some("python3".into()));
+        assert_eq!(program_name(&[]), None);
+    }
+
+    #[test]
+    fn test_foreground_overrides_earlier_evidence() {
+        let mut tracker = CliTracker::new();
+        tracker.update_from_command("zsh");
+        assert_eq!(tracker.current(), CliType::Terminal);
+
+        tracker.update_from_foreground("claude");
+        assert_eq!(tracker.current(), CliType::Claude);
+        tracker.update_from_output("Claude Code v2");
+
+        // Unrecognized programs keep the evidence
+        tracker.update_from_foreground("cli.js");
+        assert_eq!(tracker.current(), CliType::Claude);
+
+        tracker.update_from_foreground("zsh");
+        assert_eq!(tracker.current(), CliType::Terminal);
+        tracker.update_from_foreground("cli.js");
+        assert_eq!(tracker.current(), CliType::Terminal);
+    }
+}
@@ -0,0 +1,93 @@
+//! Git branch and dirty state of a session's working directory
+
+use std::time::Duration;
+use tokio::process::Command;
+
+/// `git status` is skipped for repositories slower than this
+const GIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitStatus {
+    /// Branch name, or the short commit hash when detached
+    pub branch: String,
+    /// Uncommitted changes, including untracked files
+    pub dirty: bool,
+}
+
+/// A session's current directory and the repository state there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkDir {
+    pub path: String,
+    pub git: Option<GitStatus>,
+}
+
+impl WorkDir {
+    pub async fn inspect(path: String) -> Self {
+        let git = status(&path).await;
+        Self { path, git }
+    }
+}
+
+/// Git state of `dir`, or None outside a repository (or if git is missing)
+pub async fn status(dir: &str) -> Option<GitStatus> {
+    let output = Command::new("git")
+        .args(["-C", dir, "status", "--porcelain=v2", "--branch"])
+        // Don't take the index lock away from the user's own git commands
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(GIT_TIMEOUT, output).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_status(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_status(porcelain: &str) -> Option<GitStatus> {
+    let mut head = None;
+    let mut oid = None;
+    let mut dirty = false;
+    for line in porcelain.lines() {
+        if let Some(name) = line.strip_prefix("# branch.head ") {
+            head = Some(name);
+        } else if let Some(hash) = line.strip_prefix("# branch.oid ") {
+            oid = Some(hash);
+        } else if !line.starts_with('#') {
+            dirty = true;
+        }
+    }
+    let branch = match head? {
+        "(detached)" => oid?.chars().take(7).collect(),
+        name => name.to_string(),
+    };
+    Some(GitStatus { branch, dirty })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let clean =
+            "# branch.oid 0123456789abcdef\n# branch.head main\n# branch.upstream origin/main\n";
+        assert_eq!(
+            parse_status(clean),
+            Some(GitStatus {
+                branch: "main".into(),
+                dirty: false
+            })
+        );
+
+        let detached = "# branch.oid 0123456789abcdef\n# branch.head (detached)\n? new.txt\n";
+        assert_eq!(
+            parse_status(detached),
+            Some(GitStatus {
+                branch: "0123456".into(),
+                dirty: true
+            })
+        );
+
+        assert_eq!(parse_status(""), None);
+    }
+}
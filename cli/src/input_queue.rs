@@ -0,0 +1,110 @@
+//! Prompts queued for delivery when a session becomes idle
+//!
+//! Input typed while an agent is mid-task interleaves with its output. A
+//! queued prompt is held by the daemon until the session reports the wait
+//! type it was queued for (by default "awaiting response"), then typed in
+//! followed by Enter. One prompt is delivered per wait, oldest first.
+
+use crate::detection::WaitType;
+use crate::protocol::QueuedInputItem;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+pub struct QueuedInput {
+    pub id: u64,
+    pub text: String,
+    pub deliver_on: WaitType,
+    pub queued_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct InputQueue {
+    items: VecDeque<QueuedInput>,
+    next_id: u64,
+}
+
+impl InputQueue {
+    /// Queue a prompt; returns its ID
+    pub fn push(&mut self, text: String, deliver_on: WaitType) -> u64 {
+        self.next_id += 1;
+        self.items.push_back(QueuedInput {
+            id: self.next_id,
+            text,
+            deliver_on,
+            queued_at: Utc::now(),
+        });
+        self.next_id
+    }
+
+    /// Replace the text of a queued prompt
+    pub fn edit(&mut self, id: u64, text: String) -> bool {
+        match self.items.iter_mut().find(|item| item.id == id) {
+            Some(item) => {
+                item.text = text;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel(&mut self, id: u64) -> bool {
+        let before = self.items.len();
+        self.items.retain(|item| item.id != id);
+        self.items.len() != before
+    }
+
+    /// The oldest prompt waiting for `wait_type`, removed from the queue
+    pub fn take_ready(&mut self, wait_type: WaitType) -> Option<QueuedInput> {
+        let pos = self
+            .items
+            .iter()
+            .position(|item| item.deliver_on == wait_type)?;
+        self.items.remove(pos)
+    }
+
+    pub fn items(&self) -> Vec<QueuedInputItem> {
+        self.items
+            .iter()
+            .map(|item| QueuedInputItem {
+                id: item.id,
+                text: item.text.clone(),
+                deliver_on: item.deliver_on.as_str().to_string(),
+                queued_at: item.queued_at.to_rfc3339(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivers_oldest_matching_prompt() {
+        let mut queue = InputQueue::default();
+        let first = queue.push("run the tests".into(), WaitType::AwaitingResponse);
+        queue.push("approve".into(), WaitType::ToolApproval);
+        let third = queue.push("commit".into(), WaitType::AwaitingResponse);
+
+        assert!(queue.edit(third, "commit it".into()));
+        assert_eq!(
+            queue.take_ready(WaitType::AwaitingResponse).map(|i| i.id),
+            Some(first)
+        );
+        assert_eq!(
+            queue.take_ready(WaitType::AwaitingResponse).map(|i| i.text),
+            Some("commit it".to_string())
+        );
+        assert!(queue.take_ready(WaitType::AwaitingResponse).is_none());
+        assert_eq!(queue.items().len(), 1);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut queue = InputQueue::default();
+        let id = queue.push("x".into(), WaitType::AwaitingResponse);
+        assert!(queue.cancel(id));
+        assert!(!queue.cancel(id));
+        assert!(queue.take_ready(WaitType::AwaitingResponse).is_none());
+    }
+}
@@ -0,0 +1,68 @@
+//! Key names for `mobilecli send --keys`
+//!
+//! Uses tmux-style names: `C-c` (Ctrl), `M-x` (Alt/Meta), and named keys
+//! such as `Enter`, `Tab`, `Escape` or `Up`. Arrow and editing keys are sent
+//! as the usual xterm sequences.
+
+/// Translate a key name into the bytes a terminal would send
+pub fn parse_key(name: &str) -> Option<Vec<u8>> {
+    if let Some(rest) = name.strip_prefix("M-") {
+        let mut bytes = vec![0x1b];
+        bytes.extend(parse_key(rest)?);
+        return Some(bytes);
+    }
+    if let Some(rest) = name.strip_prefix("C-") {
+        let mut chars = rest.chars();
+        let c = chars.next()?.to_ascii_uppercase();
+        if chars.next().is_some() || !('@'..='_').contains(&c) {
+            return None;
+        }
+        return Some(vec![c as u8 ^ 0x40]);
+    }
+
+    let bytes: &[u8] = match name.to_ascii_lowercase().as_str() {
+        "enter" | "return" => b"\r",
+        "tab" => b"\t",
+        "btab" | "backtab" => b"\x1b[Z",
+        "escape" | "esc" => b"\x1b",
+        "space" => b" ",
+        "bspace" | "backspace" => b"\x7f",
+        "up" => b"\x1b[A",
+        "down" => b"\x1b[B",
+        "right" => b"\x1b[C",
+        "left" => b"\x1b[D",
+        "home" => b"\x1b[H",
+        "end" => b"\x1b[F",
+        "pageup" | "ppage" => b"\x1b[5~",
+        "pagedown" | "npage" => b"\x1b[6~",
+        "insert" | "ic" => b"\x1b[2~",
+        "delete" | "dc" => b"\x1b[3~",
+        _ => {
+            // A single character is sent as itself
+            let mut chars = name.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            return Some(c.to_string().into_bytes());
+        }
+    };
+    Some(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("C-c"), Some(vec![0x03]));
+        assert_eq!(parse_key("C-D"), Some(vec![0x04]));
+        assert_eq!(parse_key("M-x"), Some(b"\x1bx".to_vec()));
+        assert_eq!(parse_key("Enter"), Some(b"\r".to_vec()));
+        assert_eq!(parse_key("Up"), Some(b"\x1b[A".to_vec()));
+        assert_eq!(parse_key("y"), Some(b"y".to_vec()));
+        assert_eq!(parse_key("C-"), None);
+        assert_eq!(parse_key("Nope"), None);
+    }
+}
@@ -2,55 +2,20 @@
 //!
 //! Similar to `screen -x` or `tmux attach` - joins an existing PTY session.
 
-use crate::daemon;
+use crate::client;
+use crate::platform;
 use crate::protocol::{ClientMessage, ServerMessage, SessionListItem};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use colored::Colorize;
 use futures_util::{SinkExt, StreamExt};
 use std::io::{self, Read, Write};
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 /// Run the link command
 pub async fn run(session_id: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-    // Ensure daemon is running
-    if !daemon::is_running() {
-        return Err("Daemon is not running. Start a session with 'mobilecli' first.".into());
-    }
-
-    let port = daemon::get_port().unwrap_or(daemon::DEFAULT_PORT);
-    let ws_url = format!("ws://127.0.0.1:{}", port);
-
     // Connect to daemon to get session list
-    let (mut ws, _) = connect_async(&ws_url).await?;
-
-    // Send hello
-    let hello = ClientMessage::Hello {
-        auth_token: None,
-        client_version: env!("CARGO_PKG_VERSION").to_string(),
-    };
-    ws.send(Message::Text(serde_json::to_string(&hello)?))
-        .await?;
-
-    // Wait for welcome and sessions list
-    let mut sessions: Vec<SessionListItem> = Vec::new();
-
-    while let Some(msg) = ws.next().await {
-        match msg? {
-            Message::Text(text) => {
-                if let Ok(server_msg) = serde_json::from_str::<ServerMessage>(&text) {
-                    match server_msg {
-                        ServerMessage::Welcome { .. } => continue,
-                        ServerMessage::Sessions { sessions: s } => {
-                            sessions = s;
-                            break;
-                        }
-                        _ => continue,
-                    }
-                }
-            }
-            _ => continue,
-        }
-    }
+    let (mut ws, sessions) = client::connect().await?;
 
     // Close initial connection
     let _ = ws.close(None).await;
@@ -64,10 +29,7 @@ pub async fn run(session_id: Option<String>) -> Result<(), Box<dyn std::error::E
     // Find session to link to
     let session = if let Some(ref id_or_name) = session_id {
         // Try to find by ID prefix or name
-        sessions.iter().find(|s| {
-            s.session_id.starts_with(id_or_name)
-                || s.name.to_lowercase().contains(&id_or_name.to_lowercase())
-        })
+        client::find_session(&sessions, id_or_name)
     } else if sessions.len() == 1 {
         // Auto-select if only one session
         sessions.first()
@@ -93,7 +55,55 @@ pub async fn run(session_id: Option<String>) -> Result<(), Box<dyn std::error::E
     );
 
     // Run linked mode
-    run_linked_mode(&ws_url, &session).await
+    let mut input_rx = spawn_stdin_reader();
+    run_linked_mode(&client::daemon_url(), &session, sessions, &mut input_rx).await
+}
+
+/// Link to a session by ID, reading keys from an existing stdin reader
+/// (used by `mobilecli watch`, which owns stdin for its whole lifetime)
+pub async fn attach(
+    session_id: &str,
+    input_rx: &mut UnboundedReceiver<Vec<u8>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut ws, sessions) = client::connect().await?;
+    let _ = ws.close(None).await;
+    let session = client::find_session(&sessions, session_id)
+        .cloned()
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    run_linked_mode(&client::daemon_url(), &session, sessions, input_rx).await
+}
+
+/// Forward raw stdin reads to a channel from a background thread
+pub fn spawn_stdin_reader() -> UnboundedReceiver<Vec<u8>> {
+    let (input_tx, input_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
+
+    // Spawn stdin reader thread with error handling
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buf = [0u8; 1024];
+        loop {
+            match stdin.read(&mut buf) {
+                Ok(0) => {
+                    // EOF - graceful shutdown
+                    tracing::debug!("stdin EOF, shutting down input reader");
+                    break;
+                }
+                Ok(n) => {
+                    if input_tx.send(buf[..n].to_vec()).is_err() {
+                        // Channel closed - main task has shut down
+                        tracing::debug!("Input channel closed, shutting down reader");
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("stdin read error: {}, shutting down reader", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    input_rx
 }
 
 /// Interactive session picker
@@ -166,6 +176,8 @@ fn show_session_picker<'a>(
 async fn run_linked_mode(
     ws_url: &str,
     session: &SessionListItem,
+    mut sessions: Vec<SessionListItem>,
+    input_rx: &mut UnboundedReceiver<Vec<u8>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Connect to daemon
     let (ws, _) = connect_async(ws_url).await?;
@@ -179,20 +191,27 @@ async fn run_linked_mode(
     tx.send(Message::Text(serde_json::to_string(&hello)?))
         .await?;
 
-    // Subscribe to session
-    let subscribe = ClientMessage::Subscribe {
-        session_id: session.session_id.clone(),
-    };
-    tx.send(Message::Text(serde_json::to_string(&subscribe)?))
-        .await?;
-
-    // Request session history
-    let history_req = ClientMessage::GetSessionHistory {
-        session_id: session.session_id.clone(),
-        max_bytes: None,
-    };
-    tx.send(Message::Text(serde_json::to_string(&history_req)?))
-        .await?;
+    // Subscribe to session (daemon replies with a screen snapshot for catch-up)
+    // and take part in its size arbitration like any other viewer
+    let mut session_id = session.session_id.clone();
+    let mut session_name = session.name.clone();
+    for msg in attach_messages(&session_id)? {
+        tx.send(msg).await?;
+    }
+    let mut resize_signal = platform::ResizeSignal::new();
+
+    // Prefix key for link commands
+    let settings = crate::setup::load_settings();
+    let prefix = parse_prefix(&settings.link_prefix).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid link_prefix '{}', using {}",
+            settings.link_prefix,
+            DEFAULT_PREFIX
+        );
+        parse_prefix(DEFAULT_PREFIX).unwrap_or(0x1d)
+    });
+    let mut keys = PrefixParser::new(prefix);
+    let mut read_only = false;
 
     // Set up raw terminal mode (Unix only for now)
     #[cfg(unix)]
@@ -206,51 +225,17 @@ async fn run_linked_mode(
 
     println!("\r{}", "─".repeat(60).dimmed());
     println!(
-        "\r{} Press {} to disconnect",
+        "\r{} Press {} then {} to detach, {} for help",
         "Linked:".green().bold(),
-        "Ctrl+D".cyan().bold()
+        prefix_label(prefix).cyan().bold(),
+        "d".cyan().bold(),
+        "?".cyan().bold()
     );
     println!("\r{}", "─".repeat(60).dimmed());
 
-    // Set up stdin reader
-    let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
-
-    // Spawn stdin reader thread with error handling
-    std::thread::spawn(move || {
-        let mut stdin = io::stdin();
-        let mut buf = [0u8; 1024];
-        loop {
-            match stdin.read(&mut buf) {
-                Ok(0) => {
-                    // EOF - graceful shutdown
-                    tracing::debug!("stdin EOF, shutting down input reader");
-                    break;
-                }
-                Ok(n) => {
-                    // Check for Ctrl+D (EOF character) - only when sent alone
-                    // Unix terminals treat Ctrl+D as EOF only on empty line
-                    if n == 1 && buf[0] == 0x04 {
-                        tracing::debug!("Ctrl+D received, disconnecting");
-                        break;
-                    }
-                    if input_tx.send(buf[..n].to_vec()).is_err() {
-                        // Channel closed - main task has shut down
-                        tracing::debug!("Input channel closed, shutting down reader");
-                        break;
-                    }
-                }
-                Err(e) => {
-                    tracing::debug!("stdin read error: {}, shutting down reader", e);
-                    break;
-                }
-            }
-        }
-    });
-
-    let session_id = session.session_id.clone();
     let mut session_ended = false;
 
-    loop {
+    'outer: loop {
         tokio::select! {
             // WebSocket messages from daemon
             result = rx.next() => {
@@ -265,14 +250,20 @@ async fn run_linked_mode(
                                         let _ = stdout.flush();
                                     }
                                 }
-                                ServerMessage::SessionHistory { session_id: sid, data, .. } if sid == session_id => {
-                                    // Display history (catch-up)
+                                ServerMessage::ScreenSnapshot { session_id: sid, data, .. } if sid == session_id => {
+                                    // Redraw the current screen (catch-up)
                                     if let Ok(bytes) = BASE64.decode(&data) {
                                         let mut stdout = io::stdout();
                                         let _ = stdout.write_all(&bytes);
                                         let _ = stdout.flush();
                                     }
                                 }
+                                ServerMessage::Sessions { sessions: list } => {
+                                    sessions = list;
+                                }
+                                ServerMessage::SessionRenamed { session_id: sid, new_name } if sid == session_id => {
+                                    session_name = new_name;
+                                }
                                 ServerMessage::SessionEnded { session_id: sid, exit_code } if sid == session_id => {
                                     session_ended = true;
                                     println!("\r\n{} Session ended (exit code: {})", "─".repeat(40).dimmed(), exit_code);
@@ -292,13 +283,100 @@ async fn run_linked_mode(
 
             // Local stdin input
             Some(input) = input_rx.recv() => {
-                let msg = ClientMessage::SendInput {
-                    session_id: session_id.clone(),
-                    text: String::from_utf8_lossy(&input).to_string(),
-                    raw: true,
-                    client_msg_id: None,
-                };
-                if tx.send(Message::Text(serde_json::to_string(&msg)?)).await.is_err() {
+                for event in keys.feed(&input) {
+                    let command = match event {
+                        KeyEvent::Input(data) => {
+                            if read_only {
+                                continue;
+                            }
+                            let msg = ClientMessage::SendInput {
+                                session_id: session_id.clone(),
+                                text: String::from_utf8_lossy(&data).to_string(),
+                                raw: true,
+                                client_msg_id: None,
+                                submit: false,
+                            };
+                            if tx.send(Message::Text(serde_json::to_string(&msg)?)).await.is_err() {
+                                break 'outer;
+                            }
+                            continue;
+                        }
+                        KeyEvent::Command(command) => command,
+                    };
+
+                    match command {
+                        LinkCommand::Detach => break 'outer,
+                        LinkCommand::ToggleReadOnly => {
+                            read_only = !read_only;
+                            let state = if read_only { "on (input is not sent)" } else { "off" };
+                            status(&format!("Read-only {}", state));
+                        }
+                        LinkCommand::Help => {
+                            print_help(prefix);
+                            wait_for_key(input_rx).await;
+                            redraw(&mut tx, &session_id).await?;
+                        }
+                        LinkCommand::List => {
+                            print_sessions(&sessions, &session_id);
+                            wait_for_key(input_rx).await;
+                            redraw(&mut tx, &session_id).await?;
+                        }
+                        LinkCommand::Rename => {
+                            let prompt = format!("Rename '{}' to:", session_name);
+                            if let Some(new_name) = prompt_line(input_rx, &prompt).await.filter(|n| !n.is_empty()) {
+                                let msg = ClientMessage::RenameSession {
+                                    session_id: session_id.clone(),
+                                    new_name,
+                                };
+                                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                            }
+                            redraw(&mut tx, &session_id).await?;
+                        }
+                        LinkCommand::Switch => {
+                            print_sessions(&sessions, &session_id);
+                            let choice = prompt_line(input_rx, "Switch to (number or name):").await;
+                            let target = choice.as_deref().and_then(|c| match c.parse::<usize>() {
+                                Ok(n) if n >= 1 => sessions.get(n - 1),
+                                _ => client::find_session(&sessions, c),
+                            });
+                            match target {
+                                Some(target) if target.session_id != session_id => {
+                                    let unsubscribe = ClientMessage::Unsubscribe {
+                                        session_id: session_id.clone(),
+                                    };
+                                    tx.send(Message::Text(serde_json::to_string(&unsubscribe)?)).await?;
+                                    session_id = target.session_id.clone();
+                                    session_name = target.name.clone();
+                                    // Clear the screen; the new session's snapshot follows
+                                    print!("\x1b[2J\x1b[H");
+                                    for msg in attach_messages(&session_id)? {
+                                        tx.send(msg).await?;
+                                    }
+                                }
+                                Some(_) => redraw(&mut tx, &session_id).await?,
+                                None => {
+                                    if choice.is_some_and(|c| !c.is_empty()) {
+                                        status("No such session");
+                                    }
+                                    redraw(&mut tx, &session_id).await?;
+                                }
+                            }
+                        }
+                        LinkCommand::Unknown(key) => {
+                            status(&format!(
+                                "Unknown command {:?}; {} ? for help",
+                                key as char,
+                                prefix_label(prefix)
+                            ));
+                        }
+                    }
+                }
+            }
+
+            // Local terminal resized
+            _ = resize_signal.recv() => {
+                let (cols, rows) = platform::terminal_size();
+                if tx.send(resize_message(&session_id, cols, rows)?).await.is_err() {
                     break;
                 }
             }
@@ -308,6 +386,17 @@ async fn run_linked_mode(
         }
     }
 
+    // Detaching: unsubscribe so our size stops constraining the session
+    if !session_ended {
+        let unsubscribe = ClientMessage::Unsubscribe {
+            session_id: session_id.clone(),
+        };
+        let _ = tx
+            .send(Message::Text(serde_json::to_string(&unsubscribe)?))
+            .await;
+        let _ = tx.close().await;
+    }
+
     // Restore terminal mode
     #[cfg(unix)]
     {
@@ -322,9 +411,232 @@ async fn run_linked_mode(
     Ok(())
 }
 
+/// Subscribe to a session and report this terminal's size for it
+fn attach_messages(session_id: &str) -> serde_json::Result<Vec<Message>> {
+    let subscribe = ClientMessage::Subscribe {
+        session_id: session_id.to_string(),
+    };
+    let (cols, rows) = platform::terminal_size();
+    Ok(vec![
+        Message::Text(serde_json::to_string(&subscribe)?),
+        resize_message(session_id, cols, rows)?,
+    ])
+}
+
+/// Ask for a fresh screen snapshot after drawing over the session
+async fn redraw<S>(tx: &mut S, session_id: &str) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: SinkExt<Message> + Unpin,
+    S::Error: std::error::Error + 'static,
+{
+    let subscribe = ClientMessage::Subscribe {
+        session_id: session_id.to_string(),
+    };
+    tx.send(Message::Text(serde_json::to_string(&subscribe)?))
+        .await?;
+    Ok(())
+}
+
+/// Print a one-line message from link mode (terminal is in raw mode)
+fn status(message: &str) {
+    print!("\r\n{} {}\r\n", "mobilecli:".cyan().bold(), message);
+    let _ = io::stdout().flush();
+}
+
+fn print_help(prefix: u8) {
+    let key = prefix_label(prefix);
+    print!("\r\n{}\r\n", "Link commands".bold());
+    for (k, what) in [
+        ("d", "detach"),
+        ("s", "switch session"),
+        ("l", "list sessions"),
+        ("n", "rename session"),
+        ("r", "toggle read-only"),
+        ("?", "this help"),
+    ] {
+        print!("  {} {}  {}\r\n", key, k.cyan().bold(), what);
+    }
+    print!("  {} {}  send {} itself\r\n", key, key, key);
+    let _ = io::stdout().flush();
+}
+
+fn print_sessions(sessions: &[SessionListItem], current: &str) {
+    print!("\r\n");
+    for (i, session) in sessions.iter().enumerate() {
+        let marker = if session.session_id == current {
+            "*"
+        } else {
+            " "
+        };
+        print!(
+            "{} {}. {} {}\r\n",
+            marker.green(),
+            (i + 1).to_string().bold(),
+            session.name.green(),
+            session.project_path.dimmed()
+        );
+    }
+    let _ = io::stdout().flush();
+}
+
+/// Keep something drawn over the session visible until a key is pressed
+async fn wait_for_key(input_rx: &mut UnboundedReceiver<Vec<u8>>) {
+    print!("{}", "Press any key to return".dimmed());
+    let _ = io::stdout().flush();
+    let _ = input_rx.recv().await;
+}
+
+/// Read a line typed in raw mode. `None` if cancelled (Esc, Ctrl-C) or
+/// stdin closed.
+async fn prompt_line(input_rx: &mut UnboundedReceiver<Vec<u8>>, prompt: &str) -> Option<String> {
+    let mut stdout = io::stdout();
+    let _ = write!(stdout, "\r\n{} ", prompt.bold());
+    let _ = stdout.flush();
+
+    let mut line = Vec::new();
+    while let Some(data) = input_rx.recv().await {
+        for b in data {
+            match b {
+                b'\r' | b'\n' => {
+                    let _ = write!(stdout, "\r\n");
+                    return Some(String::from_utf8_lossy(&line).trim().to_string());
+                }
+                0x1b | 0x03 => {
+                    let _ = write!(stdout, "\r\n");
+                    return None;
+                }
+                0x7f | 0x08 => {
+                    // Drop a whole UTF-8 character
+                    while let Some(last) = line.pop() {
+                        if last & 0xc0 != 0x80 {
+                            break;
+                        }
+                    }
+                    let _ = write!(stdout, "\x08 \x08");
+                }
+                b if b >= 0x20 => {
+                    line.push(b);
+                    let _ = stdout.write_all(&[b]);
+                }
+                _ => {}
+            }
+        }
+        let _ = stdout.flush();
+    }
+    None
+}
+
+/// Prefix key used when `link_prefix` isn't set
+pub const DEFAULT_PREFIX: &str = "C-]";
+
+/// Parse a prefix key like `C-]`, `Ctrl-a` or `^B` into its control byte
+pub fn parse_prefix(s: &str) -> Option<u8> {
+    let s = s.trim();
+    let key = ["C-", "c-", "Ctrl-", "ctrl-", "Ctrl+", "ctrl+", "^"]
+        .iter()
+        .find_map(|p| s.strip_prefix(p))?;
+    let mut chars = key.chars();
+    let c = chars.next()?.to_ascii_uppercase();
+    if chars.next().is_some() || !('@'..='_').contains(&c) {
+        return None;
+    }
+    Some(c as u8 ^ 0x40)
+}
+
+/// Human-readable name of a prefix byte, e.g. `Ctrl+]`
+fn prefix_label(prefix: u8) -> String {
+    format!("Ctrl+{}", (prefix ^ 0x40) as char)
+}
+
+/// Commands entered after the prefix key
+#[derive(Debug, PartialEq, Eq)]
+enum LinkCommand {
+    Detach,
+    Switch,
+    List,
+    Rename,
+    ToggleReadOnly,
+    Help,
+    Unknown(u8),
+}
+
+impl LinkCommand {
+    fn from_key(key: u8) -> Self {
+        match key {
+            b'd' => LinkCommand::Detach,
+            b's' => LinkCommand::Switch,
+            b'l' => LinkCommand::List,
+            b'n' => LinkCommand::Rename,
+            b'r' => LinkCommand::ToggleReadOnly,
+            b'?' => LinkCommand::Help,
+            other => LinkCommand::Unknown(other),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum KeyEvent {
+    /// Bytes to forward to the session
+    Input(Vec<u8>),
+    Command(LinkCommand),
+}
+
+/// Splits stdin into session input and prefix commands. The prefix may
+/// arrive in one read and the command key in the next.
+struct PrefixParser {
+    prefix: u8,
+    armed: bool,
+}
+
+impl PrefixParser {
+    fn new(prefix: u8) -> Self {
+        Self {
+            prefix,
+            armed: false,
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Vec<KeyEvent> {
+        let mut events = Vec::new();
+        let mut input = Vec::new();
+        for &b in data {
+            if self.armed {
+                self.armed = false;
+                if b == self.prefix {
+                    // Prefix twice sends it literally
+                    input.push(b);
+                } else {
+                    if !input.is_empty() {
+                        events.push(KeyEvent::Input(std::mem::take(&mut input)));
+                    }
+                    events.push(KeyEvent::Command(LinkCommand::from_key(b)));
+                }
+            } else if b == self.prefix {
+                self.armed = true;
+            } else {
+                input.push(b);
+            }
+        }
+        if !input.is_empty() {
+            events.push(KeyEvent::Input(input));
+        }
+        events
+    }
+}
+
+/// Report this terminal's size for a session
+fn resize_message(session_id: &str, cols: u16, rows: u16) -> serde_json::Result<Message> {
+    let msg = ClientMessage::PtyResize {
+        session_id: session_id.to_string(),
+        cols,
+        rows,
+    };
+    Ok(Message::Text(serde_json::to_string(&msg)?))
+}
+
 /// Set up raw terminal mode (Unix)
 #[cfg(unix)]
-fn setup_raw_mode(fd: i32) -> io::Result<nix::sys::termios::Termios> {
+pub fn setup_raw_mode(fd: i32) -> io::Result<nix::sys::termios::Termios> {
     use nix::sys::termios::{self, InputFlags, LocalFlags, SetArg};
     use std::os::fd::BorrowedFd;
 
@@ -352,7 +664,7 @@ fn setup_raw_mode(fd: i32) -> io::Result<nix::sys::termios::Termios> {
 
 /// Restore terminal mode (Unix)
 #[cfg(unix)]
-fn restore_terminal_mode(fd: i32, original: &nix::sys::termios::Termios) -> io::Result<()> {
+pub fn restore_terminal_mode(fd: i32, original: &nix::sys::termios::Termios) -> io::Result<()> {
     use nix::sys::termios::{self, SetArg};
     use std::os::fd::BorrowedFd;
 
@@ -363,3 +675,43 @@ fn restore_terminal_mode(fd: i32, original: &nix::sys::termios::Termios) -> io::
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(parse_prefix("C-]"), Some(0x1d));
+        assert_eq!(parse_prefix("Ctrl-a"), Some(0x01));
+        assert_eq!(parse_prefix("^B"), Some(0x02));
+        assert_eq!(parse_prefix("C-ab"), None);
+        assert_eq!(parse_prefix("x"), None);
+    }
+
+    #[test]
+    fn test_prefix_commands_split_input() {
+        let mut keys = PrefixParser::new(0x1d);
+        assert_eq!(
+            keys.feed(b"ls\x1dd"),
+            vec![
+                KeyEvent::Input(b"ls".to_vec()),
+                KeyEvent::Command(LinkCommand::Detach)
+            ]
+        );
+        // Ctrl-D goes to the session
+        assert_eq!(keys.feed(b"\x04"), vec![KeyEvent::Input(vec![0x04])]);
+    }
+
+    #[test]
+    fn test_prefix_across_reads_and_literal() {
+        let mut keys = PrefixParser::new(0x1d);
+        assert_eq!(keys.feed(b"\x1d"), vec![]);
+        assert_eq!(keys.feed(b"\x1d"), vec![KeyEvent::Input(vec![0x1d])]);
+        assert_eq!(keys.feed(b"\x1d"), vec![]);
+        assert_eq!(
+            keys.feed(b"s"),
+            vec![KeyEvent::Command(LinkCommand::Switch)]
+        );
+    }
+}
@@ -4,20 +4,41 @@
 //!   mobilecli              # Start your shell with mobile streaming
 //!   mobilecli <command>    # Run a command with mobile streaming
 //!   mobilecli -n "Work"    # Name your session
+//!   mobilecli --record     # Record the session as an asciicast file
+//!   mobilecli --scrollback 4M  # Keep 4MB of scrollback for this session
 //!   mobilecli setup        # Run setup wizard (shows QR code)
 //!   mobilecli status       # Show active sessions
+//!   mobilecli watch        # Dashboard of all sessions
+//!   mobilecli send X "hi" -e  # Type into a session
+//!   mobilecli transcript X # Print readable text of a session
+//!   mobilecli search "err" # Search output of all sessions
 //!   mobilecli daemon       # Run the background server
 //!   mobilecli --help       # Show help
 
+mod batching;
+mod client;
 mod daemon;
 mod detection;
+mod git;
+mod input_queue;
+mod keys;
 mod link;
+mod output_log;
 mod platform;
 mod protocol;
 mod pty_wrapper;
 mod qr;
+mod recording;
+mod screen;
+mod scrollback;
+mod search;
 mod session;
 mod setup;
+mod shell_integration;
+mod sizing;
+mod transcript;
+mod triggers;
+mod watch;
 
 use clap::{Parser, Subcommand};
 use colored::Colorize;
@@ -55,12 +76,71 @@ struct RunArgs {
     /// Run setup wizard and show QR code for pairing
     #[arg(long = "setup")]
     setup: bool,
+
+    /// Record the session as an asciicast v2 file in ~/.mobilecli/recordings
+    #[arg(long = "record")]
+    record: bool,
+
+    /// Scrollback kept by the daemon for this session (e.g. 512K, 4M)
+    #[arg(long = "scrollback", value_name = "SIZE", value_parser = parse_size)]
+    scrollback: Option<usize>,
+
+    /// Start a session even when already inside one
+    #[arg(long = "nested")]
+    nested: bool,
+
+    /// Notify the phone when a command running at least this long finishes (e.g. 30s, 5m; 0 = never)
+    #[arg(long = "notify-after", value_name = "DURATION", value_parser = parse_notify_after)]
+    notify_after: Option<u64>,
+}
+
+/// Parse a `--notify-after` duration into seconds
+fn parse_notify_after(input: &str) -> Result<u64, String> {
+    search::parse_duration(input)
+        .map(|d| d.as_secs())
+        .ok_or_else(|| format!("invalid duration '{}' (use e.g. 30s, 5m, 1h)", input))
+}
+
+/// Exit status when the session couldn't be started. The auto-wrap rc
+/// snippet gets a distinct one so it keeps the plain shell instead of exiting.
+fn startup_failure() -> ExitCode {
+    if std::env::var_os(shell_integration::AUTO_WRAP_ENV).is_some() {
+        ExitCode::from(shell_integration::AUTO_WRAP_FAILED)
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Parse a byte size like `65536`, `512K` or `4M`
+fn parse_size(input: &str) -> Result<usize, String> {
+    let input = input.trim();
+    let (number, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => input.split_at(idx),
+        None => (input, ""),
+    };
+    let value: usize = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", input))?;
+    let multiplier = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size unit '{}' (use K, M or G)", unit)),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", input))
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Show active streaming sessions
-    Status,
+    Status {
+        /// Print machine-readable JSON (for prompts, status bars, scripts)
+        #[arg(long)]
+        json: bool,
+    },
     /// Run the setup wizard and show QR code for pairing
     Setup,
     /// Show QR code for mobile pairing
@@ -78,6 +158,106 @@ enum Commands {
         /// Session ID or name to link to (optional - shows picker if omitted)
         session: Option<String>,
     },
+    /// Rename a session (the one you are in by default)
+    Rename {
+        /// New name
+        name: String,
+        /// Session ID or name (defaults to $MOBILECLI_SESSION_ID)
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+    /// Send a notification to your phone (in-app and push)
+    Notify {
+        #[arg(long)]
+        title: String,
+        #[arg(long, default_value = "")]
+        body: String,
+        /// Session it is about (defaults to $MOBILECLI_SESSION_ID, if set)
+        #[arg(short, long)]
+        session: Option<String>,
+        #[arg(long, default_value = "normal", value_parser = ["normal", "high"])]
+        priority: String,
+    },
+    /// Type into a running session (text, then keys, then Enter)
+    Send {
+        /// Session ID or name
+        session: String,
+        /// Text to type
+        text: Option<String>,
+        /// Press Enter after the text and keys
+        #[arg(short, long)]
+        enter: bool,
+        /// Keys to press, tmux-style (e.g. C-c, M-x, Up, Escape); comma-separated or repeated
+        #[arg(short, long, value_delimiter = ',')]
+        keys: Vec<String>,
+        /// Hold the input until the session waits (default: awaiting_response), then send it with Enter
+        #[arg(
+            long,
+            value_name = "WAIT_TYPE",
+            num_args = 0..=1,
+            default_missing_value = "awaiting_response"
+        )]
+        queue: Option<String>,
+    },
+    /// Full-screen dashboard of all sessions (approve prompts, jump into link)
+    Watch,
+    /// Get notified when a regex shows up in a session's output
+    #[command(name = "watch-for")]
+    WatchFor {
+        /// Session ID or name
+        session: String,
+        /// Regex matched against the session's output (ANSI stripped)
+        #[arg(required_unless_present_any = ["list", "remove"])]
+        pattern: Option<String>,
+        /// Shown instead of the pattern in notifications
+        #[arg(long)]
+        name: Option<String>,
+        /// Also send a push notification
+        #[arg(long)]
+        push: bool,
+        /// Minimum time between two notifications (default 60s)
+        #[arg(long, value_name = "DURATION", value_parser = parse_notify_after)]
+        cooldown: Option<u64>,
+        /// List the session's triggers
+        #[arg(long, conflicts_with_all = ["pattern", "remove"])]
+        list: bool,
+        /// Remove a trigger by ID
+        #[arg(long, value_name = "ID", conflicts_with = "pattern")]
+        remove: Option<u64>,
+    },
+    /// Print shell integration for your rc file (command start/finish, current directory)
+    Init {
+        #[arg(value_enum)]
+        shell: shell_integration::Shell,
+        /// Also start every interactive shell under mobilecli (see auto_wrap_* settings)
+        #[arg(long)]
+        auto: bool,
+    },
+    /// Print a session's output as plain text (ANSI stripped, lines reconstructed)
+    Transcript {
+        /// Session ID or name
+        session: String,
+        /// Only print the last N lines
+        #[arg(short = 'n', long = "lines")]
+        last_lines: Option<usize>,
+    },
+    /// Search session output (live sessions and persisted logs)
+    Search {
+        /// Regular expression to search for
+        pattern: String,
+        /// Only search this session (ID prefix or name)
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Only match output from this recent period (e.g. 30m, 2h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+        /// Case-insensitive match
+        #[arg(short = 'i', long = "ignore-case")]
+        ignore_case: bool,
+        /// Maximum number of matches to show
+        #[arg(long = "max", default_value_t = search::DEFAULT_MAX_RESULTS)]
+        max_results: usize,
+    },
 }
 
 #[tokio::main]
@@ -109,10 +289,17 @@ async fn main() -> ExitCode {
     // Handle subcommands
     if let Some(command) = &cli.command {
         return match command {
-            Commands::Status => {
+            Commands::Status { json: false } => {
                 show_status();
                 ExitCode::SUCCESS
             }
+            Commands::Status { json: true } => match show_status_json().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {}", "Error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
             Commands::Setup => match run_setup().await {
                 Ok(_) => ExitCode::SUCCESS,
                 Err(e) => {
@@ -152,18 +339,140 @@ async fn main() -> ExitCode {
                     ExitCode::FAILURE
                 }
             },
+            Commands::Notify {
+                title,
+                body,
+                session,
+                priority,
+            } => match send_notification(title, body, session.clone(), priority).await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {}", "Error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
+            Commands::Rename { name, session } => {
+                match rename_session(session.as_deref(), name).await {
+                    Ok(_) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        eprintln!("{}: {}", "Error".red().bold(), e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            Commands::WatchFor {
+                session,
+                pattern,
+                name,
+                push,
+                cooldown,
+                list,
+                remove,
+            } => {
+                let action = match (pattern, remove) {
+                    _ if *list => TriggerAction::List,
+                    (_, Some(id)) => TriggerAction::Remove(*id),
+                    (Some(pattern), None) => TriggerAction::Add {
+                        pattern: pattern.clone(),
+                        name: name.clone(),
+                        push: *push,
+                        cooldown_secs: *cooldown,
+                    },
+                    (None, None) => unreachable!("clap requires a pattern"),
+                };
+                match watch_for(session, action).await {
+                    Ok(_) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        eprintln!("{}: {}", "Error".red().bold(), e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            Commands::Send {
+                session,
+                text,
+                enter,
+                keys,
+                queue,
+            } => match send_input(session, text.as_deref(), keys, *enter, queue.as_deref()).await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {}", "Error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
+            Commands::Init { shell, auto } => {
+                if *auto {
+                    let settings = setup::load_settings();
+                    print!("{}", shell_integration::auto_wrap_script(*shell, &settings));
+                }
+                print!("{}", shell_integration::init_script(*shell));
+                ExitCode::SUCCESS
+            }
+            Commands::Watch => match watch::run().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {}", "Watch error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
+            Commands::Transcript {
+                session,
+                last_lines,
+            } => match show_transcript(session, *last_lines).await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {}", "Error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
+            Commands::Search {
+                pattern,
+                session,
+                since,
+                ignore_case,
+                max_results,
+            } => match run_search(
+                pattern,
+                session.clone(),
+                since.as_deref(),
+                *ignore_case,
+                *max_results,
+            )
+            .await
+            {
+                Ok(true) => ExitCode::SUCCESS,
+                Ok(false) => ExitCode::FAILURE,
+                Err(e) => {
+                    eprintln!("{}: {}", "Search error".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            },
         };
     }
 
     // Get run args (or defaults)
     let run_args = cli.run_args.unwrap_or_default();
 
+    // A session inside a session streams everything twice
+    if !run_args.nested {
+        if let Some(session) = client::current_live_session().await {
+            eprintln!(
+                "{} Already inside mobilecli session '{}'. Use {} to start another one anyway.",
+                "Error:".red().bold(),
+                session.name,
+                "--nested".cyan()
+            );
+            return startup_failure();
+        }
+    }
+
     // Ensure daemon is running
     if !daemon::is_running() {
         // Start daemon in background
         if let Err(e) = start_daemon_background().await {
             eprintln!("{}: {}", "Failed to start daemon".red().bold(), e);
-            return ExitCode::FAILURE;
+            return startup_failure();
         }
     }
 
@@ -208,13 +517,16 @@ async fn main() -> ExitCode {
         args,
         session_name: session_name.clone(),
         quiet: run_args.quiet,
+        record: run_args.record,
+        scrollback_bytes: run_args.scrollback,
+        notify_after_secs: run_args.notify_after,
     };
 
     match pty_wrapper::run_wrapped(wrap_config).await {
         Ok(exit_code) => ExitCode::from(exit_code as u8),
         Err(e) => {
             eprintln!("{}: {}", "Error".red().bold(), e);
-            ExitCode::FAILURE
+            startup_failure()
         }
     }
 }
@@ -333,8 +645,414 @@ fn show_status() {
                 s.name.bold(),
                 s.command.dimmed()
             );
+            if let Some(cwd) = &s.cwd {
+                let branch = match (&s.git_branch, s.git_dirty) {
+                    (Some(branch), Some(true)) => format!(" ({}*)", branch),
+                    (Some(branch), _) => format!(" ({})", branch),
+                    _ => String::new(),
+                };
+                println!("    {}{}", cwd.dimmed(), branch.yellow());
+            }
+        }
+    }
+}
+
+/// Print daemon and session status as JSON
+async fn show_status_json() -> Result<(), Box<dyn std::error::Error>> {
+    let running = daemon::is_running();
+    let port = daemon::get_port().unwrap_or(daemon::DEFAULT_PORT);
+    let daemon_info = if running {
+        serde_json::json!({
+            "running": true,
+            "pid": daemon::get_pid(),
+            "port": port,
+            "bind": format!("{}:{}", daemon::BIND_ADDR, port),
+        })
+    } else {
+        serde_json::json!({ "running": false })
+    };
+
+    let sessions = if running {
+        let (mut ws, sessions) = client::connect().await?;
+        let _ = ws.close(None).await;
+        sessions
+    } else {
+        Vec::new()
+    };
+
+    let now = chrono::Utc::now();
+    let sessions: Vec<serde_json::Value> = sessions
+        .into_iter()
+        .map(|s| {
+            let uptime_secs = chrono::DateTime::parse_from_rfc3339(&s.started_at)
+                .map(|t| now.signed_duration_since(t).num_seconds().max(0))
+                .unwrap_or(0);
+            serde_json::json!({
+                "session_id": s.session_id,
+                "name": s.name,
+                "command": s.command,
+                "project_path": s.project_path,
+                "cwd": s.cwd,
+                "git_branch": s.git_branch,
+                "git_dirty": s.git_dirty,
+                "cli_type": s.cli_type,
+                "foreground_command": s.foreground_command,
+                "started_at": s.started_at,
+                "uptime_secs": uptime_secs,
+                "waiting_state": s.waiting_state,
+                "viewers": s.viewers,
+                "scrollback_bytes": s.scrollback_bytes,
+                "scrollback_max_bytes": s.scrollback_max_bytes,
+                "size_policy": s.size_policy,
+            })
+        })
+        .collect();
+
+    let status = serde_json::json!({
+        "daemon": daemon_info,
+        "sessions": sessions,
+    });
+    println!("{}", serde_json::to_string_pretty(&status)?);
+    Ok(())
+}
+
+/// Type text and keys into a session as a local client
+async fn send_input(
+    session: &str,
+    text: Option<&str>,
+    keys: &[String],
+    enter: bool,
+    queue: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = text.unwrap_or_default().as_bytes().to_vec();
+    for key in keys {
+        let bytes = keys::parse_key(key).ok_or_else(|| format!("Unknown key '{}'", key))?;
+        input.extend(bytes);
+    }
+    // Enter is pressed by the daemon, a moment after the text (and always
+    // for queued input)
+    if input.is_empty() && !enter {
+        return Err("Nothing to send (give text, --keys or --enter)".into());
+    }
+
+    let (mut ws, sessions) = client::connect().await?;
+    let session = client::find_session(&sessions, session)
+        .ok_or_else(|| format!("No session matching '{}'", session))?;
+    let session_id = session.session_id.clone();
+    let text = String::from_utf8(input)?;
+
+    let Some(deliver_on) = queue else {
+        client::send(
+            &mut ws,
+            &protocol::ClientMessage::SendInput {
+                session_id,
+                text,
+                raw: true,
+                client_msg_id: None,
+                submit: enter,
+            },
+        )
+        .await?;
+        let _ = ws.close(None).await;
+        return Ok(());
+    };
+
+    client::send(
+        &mut ws,
+        &protocol::ClientMessage::QueueInput {
+            session_id: session_id.clone(),
+            text,
+            deliver_on: Some(deliver_on.to_string()),
+        },
+    )
+    .await?;
+    // The queue is broadcast once the daemon has accepted (or delivered) it
+    let result = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::InputQueue { session_id: id, .. } if id == session_id => {
+            Some(Ok(()))
+        }
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before the input was queued")?;
+    let _ = ws.close(None).await;
+    result?;
+    println!("Queued for {} (sent on {})", session.name, deliver_on);
+    Ok(())
+}
+
+enum TriggerAction {
+    Add {
+        pattern: String,
+        name: Option<String>,
+        push: bool,
+        cooldown_secs: Option<u64>,
+    },
+    Remove(u64),
+    List,
+}
+
+/// Add, remove or list a session's output triggers
+async fn watch_for(session: &str, action: TriggerAction) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut ws, sessions) = client::connect().await?;
+    let session = client::find_session(&sessions, session)
+        .ok_or_else(|| format!("No session matching '{}'", session))?;
+    let session_id = session.session_id.clone();
+
+    let msg = match &action {
+        TriggerAction::Add {
+            pattern,
+            name,
+            push,
+            cooldown_secs,
+        } => protocol::ClientMessage::AddTrigger {
+            session_id: session_id.clone(),
+            pattern: pattern.clone(),
+            name: name.clone(),
+            push: *push,
+            cooldown_secs: *cooldown_secs,
+        },
+        TriggerAction::Remove(id) => protocol::ClientMessage::RemoveTrigger {
+            session_id: session_id.clone(),
+            id: *id,
+        },
+        TriggerAction::List => protocol::ClientMessage::GetTriggers {
+            session_id: session_id.clone(),
+        },
+    };
+    client::send(&mut ws, &msg).await?;
+    // Additions are answered with the new ID; other changes are confirmed by
+    // the daemon broadcasting the new list
+    let adding = matches!(action, TriggerAction::Add { .. });
+    let result = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::TriggerAdded {
+            session_id: sid,
+            id,
+        } if sid == session_id => Some(Ok((Some(id), Vec::new()))),
+        protocol::ServerMessage::Triggers {
+            session_id: id,
+            items,
+        } if id == session_id && !adding => Some(Ok((None, items))),
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before the daemon answered")?;
+    let _ = ws.close(None).await;
+    let (added_id, items) = result?;
+
+    match action {
+        TriggerAction::Add { pattern, .. } => {
+            println!(
+                "Watching {} for /{}/ (trigger {})",
+                session.name,
+                pattern,
+                added_id.unwrap_or_default()
+            );
+        }
+        TriggerAction::Remove(id) => println!("Removed trigger {} from {}", id, session.name),
+        TriggerAction::List if items.is_empty() => println!("No triggers on {}", session.name),
+        TriggerAction::List => {
+            for t in items {
+                let mut details = vec![format!("cooldown {}s", t.cooldown_secs)];
+                if t.push {
+                    details.push("push".to_string());
+                }
+                if let Some(cli) = t.cli {
+                    details.push(format!("{} only", cli));
+                }
+                if t.from_config {
+                    details.push("from settings".to_string());
+                }
+                let name = t.name.map(|n| format!("{} ", n)).unwrap_or_default();
+                println!(
+                    "{:>3}  {}/{}/  {}",
+                    t.id,
+                    name,
+                    t.pattern,
+                    details.join(", ").dimmed()
+                );
+            }
         }
     }
+    Ok(())
+}
+
+/// Send a notification through the daemon and wait until it went out
+async fn send_notification(
+    title: &str,
+    body: &str,
+    session: Option<String>,
+    priority: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut ws, sessions) = client::connect().await?;
+    let session_id = match session.or_else(client::current_session_id) {
+        Some(session) => Some(
+            client::find_session(&sessions, &session)
+                .ok_or_else(|| format!("No session matching '{}'", session))?
+                .session_id
+                .clone(),
+        ),
+        None => None,
+    };
+
+    client::send(
+        &mut ws,
+        &protocol::ClientMessage::SendNotification {
+            title: title.to_string(),
+            body: body.to_string(),
+            session_id,
+            priority: Some(priority.to_string()),
+        },
+    )
+    .await?;
+    // Every client gets the notification, including this one
+    let result = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::CustomNotification { .. } => Some(Ok(())),
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before the notification was sent")?;
+    let _ = ws.close(None).await;
+    result?;
+    Ok(())
+}
+
+/// Rename a session and wait for the daemon to confirm
+async fn rename_session(
+    session: Option<&str>,
+    new_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let session = client::session_or_current(session)?;
+    let (mut ws, sessions) = client::connect().await?;
+    let session = client::find_session(&sessions, &session)
+        .ok_or_else(|| format!("No session matching '{}'", session))?;
+
+    client::send(
+        &mut ws,
+        &protocol::ClientMessage::RenameSession {
+            session_id: session.session_id.clone(),
+            new_name: new_name.to_string(),
+        },
+    )
+    .await?;
+    let result = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::SessionRenamed { .. } => Some(Ok(())),
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before the rename was confirmed")?;
+    let _ = ws.close(None).await;
+    result?;
+    println!("Renamed '{}' to '{}'", session.name, new_name);
+    Ok(())
+}
+
+/// Print the plain-text transcript of a session
+async fn show_transcript(
+    session: &str,
+    last_lines: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut ws, sessions) = client::connect().await?;
+    let session = client::find_session(&sessions, session)
+        .ok_or_else(|| format!("No session matching '{}'", session))?;
+
+    client::send(
+        &mut ws,
+        &protocol::ClientMessage::GetTranscript {
+            session_id: session.session_id.clone(),
+            last_lines,
+        },
+    )
+    .await?;
+
+    let lines = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::Transcript { lines, .. } => Some(Ok(lines)),
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before transcript was received")??;
+
+    for line in lines {
+        println!("{}", line);
+    }
+    let _ = ws.close(None).await;
+    Ok(())
+}
+
+/// Search session output; returns whether anything matched (like grep)
+async fn run_search(
+    pattern: &str,
+    session: Option<String>,
+    since: Option<&str>,
+    ignore_case: bool,
+    max_results: usize,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let since_secs = match since {
+        Some(s) => Some(
+            search::parse_duration(s)
+                .ok_or_else(|| format!("Invalid duration '{}' (use e.g. 30m, 2h, 1d)", s))?
+                .as_secs(),
+        ),
+        None => None,
+    };
+
+    let (mut ws, _) = client::connect().await?;
+    client::send(
+        &mut ws,
+        &protocol::ClientMessage::SearchSession {
+            pattern: pattern.to_string(),
+            session_id: session,
+            since_secs,
+            case_insensitive: ignore_case,
+            max_results: Some(max_results),
+        },
+    )
+    .await?;
+
+    let (matches, truncated) = client::recv_until(&mut ws, |msg| match msg {
+        protocol::ServerMessage::SearchResults {
+            matches, truncated, ..
+        } => Some(Ok((matches, truncated))),
+        protocol::ServerMessage::Error { message, .. } => Some(Err(message)),
+        _ => None,
+    })
+    .await?
+    .ok_or("Connection closed before search results were received")??;
+    let _ = ws.close(None).await;
+
+    for m in &matches {
+        let time = chrono::DateTime::parse_from_rfc3339(&m.timestamp)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{} {} {} {}",
+            m.session_name.cyan(),
+            format!(
+                "{}:{}",
+                &m.session_id[..8.min(m.session_id.len())],
+                m.line_number + 1
+            )
+            .dimmed(),
+            time.dimmed(),
+            m.line
+        );
+    }
+    if truncated {
+        println!(
+            "{}",
+            format!("(showing the last {} matches)", matches.len()).dimmed()
+        );
+    }
+    Ok(!matches.is_empty())
 }
 
 /// Run the setup wizard
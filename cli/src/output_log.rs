@@ -0,0 +1,267 @@
+//! Persisted plain-text session logs
+//!
+//! Each session's reconstructed output lines are appended to
+//! `~/.mobilecli/logs/<session_id>.log` so they stay searchable after they
+//! scroll out of the in-memory scrollback or the session ends.
+//!
+//! File format: a JSON header line, then one line per output line:
+//! `<unix_ms>\t<line_number>\t<stream_offset>\t<text>`
+//!
+//! Lines are written by a background thread so disk I/O never blocks the
+//! session's task.
+
+use crate::platform;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Rotate a log once it grows past this size (one rotated file is kept)
+const LOG_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Longest written lines wait before being flushed to disk; a search may miss
+/// at most this much recent output
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Logs older than this are removed when the daemon starts
+pub const LOG_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Logs directory path (cross-platform)
+pub fn logs_dir() -> PathBuf {
+    platform::config_dir().join("logs")
+}
+
+fn log_path(session_id: &str) -> PathBuf {
+    logs_dir().join(format!("{}.log", session_id))
+}
+
+fn rotated_path(session_id: &str) -> PathBuf {
+    logs_dir().join(format!("{}.log.1", session_id))
+}
+
+/// Header line identifying the session a log belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogHeader {
+    pub session_id: String,
+    pub name: String,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// One persisted output line
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    pub line_number: usize,
+    pub offset: u64,
+    pub text: String,
+}
+
+/// Append-only writer for a session log
+pub struct OutputLog {
+    /// Formatted entries for the writer thread
+    entries: mpsc::Sender<String>,
+}
+
+impl OutputLog {
+    /// Create (or truncate) the log for a session
+    pub fn create(header: LogHeader) -> io::Result<Self> {
+        std::fs::create_dir_all(logs_dir())?;
+        let mut writer = LogWriter {
+            writer: LogWriter::open(&header)?,
+            header,
+            bytes_written: 0,
+        };
+        writer.write_header()?;
+        writer.writer.flush()?;
+
+        let (entries, rx) = mpsc::channel();
+        std::thread::spawn(move || writer.run(rx));
+        Ok(Self { entries })
+    }
+
+    /// Append committed lines; `first_line_number` is the transcript line
+    /// number of `lines[0]` and `offset` the stream offset at commit time
+    pub fn append(
+        &mut self,
+        lines: &[String],
+        first_line_number: usize,
+        offset: u64,
+    ) -> io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let ts = Utc::now().timestamp_millis();
+        let mut entries = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            entries.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                ts,
+                first_line_number + i,
+                offset,
+                line
+            ));
+        }
+        // The writer thread only goes away after a write error
+        self.entries
+            .send(entries)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "output log writer stopped"))
+    }
+}
+
+/// The writer thread's side of a session log
+struct LogWriter {
+    header: LogHeader,
+    writer: BufWriter<File>,
+    bytes_written: u64,
+}
+
+impl LogWriter {
+    fn open(header: &LogHeader) -> io::Result<BufWriter<File>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(log_path(&header.session_id))?;
+        Ok(BufWriter::new(file))
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let header = serde_json::to_string(&self.header)?;
+        writeln!(self.writer, "{}", header)?;
+        self.bytes_written += header.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Append entries, flushing once output pauses or `FLUSH_INTERVAL` has
+    /// passed. Ends when the log is dropped or a write fails.
+    fn run(mut self, rx: mpsc::Receiver<String>) {
+        let mut last_flush = Instant::now();
+        loop {
+            let result = match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(entries) => self.write(&entries).and_then(|_| {
+                    if last_flush.elapsed() >= FLUSH_INTERVAL {
+                        last_flush = Instant::now();
+                        self.writer.flush()
+                    } else {
+                        Ok(())
+                    }
+                }),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    last_flush = Instant::now();
+                    self.writer.flush()
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    let _ = self.writer.flush();
+                    return;
+                }
+            };
+            if let Err(e) = result {
+                tracing::warn!("Output log write failed: {}", e);
+                return;
+            }
+        }
+    }
+
+    fn write(&mut self, entries: &str) -> io::Result<()> {
+        self.writer.write_all(entries.as_bytes())?;
+        self.bytes_written += entries.len() as u64;
+        if self.bytes_written > LOG_MAX_BYTES {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        std::fs::rename(
+            log_path(&self.header.session_id),
+            rotated_path(&self.header.session_id),
+        )?;
+        self.writer = Self::open(&self.header)?;
+        self.bytes_written = 0;
+        self.write_header()
+    }
+}
+
+/// Session IDs that have logs on disk
+pub fn logged_sessions() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(logs_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_str()
+                .and_then(|n| n.strip_suffix(".log"))
+                .map(|s| s.to_string())
+        })
+        .collect()
+}
+
+/// Read the header and lines of a session log (rotated part first)
+pub fn read_log(session_id: &str) -> io::Result<(LogHeader, Vec<LogLine>)> {
+    let mut header = None;
+    let mut lines = Vec::new();
+
+    for path in [rotated_path(session_id), log_path(session_id)] {
+        let file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let mut reader = BufReader::new(file).lines();
+        if let Some(first) = reader.next() {
+            header = serde_json::from_str::<LogHeader>(&first?).ok().or(header);
+        }
+        for line in reader {
+            if let Some(parsed) = parse_line(&line?) {
+                lines.push(parsed);
+            }
+        }
+    }
+
+    let header = header.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No log for session {}", session_id),
+        )
+    })?;
+    Ok((header, lines))
+}
+
+fn parse_line(line: &str) -> Option<LogLine> {
+    let mut parts = line.splitn(4, '\t');
+    let ts: i64 = parts.next()?.parse().ok()?;
+    let line_number = parts.next()?.parse().ok()?;
+    let offset = parts.next()?.parse().ok()?;
+    let text = parts.next().unwrap_or("").to_string();
+    Some(LogLine {
+        timestamp: Utc.timestamp_millis_opt(ts).single()?,
+        line_number,
+        offset,
+        text,
+    })
+}
+
+/// Remove logs that have not been written to within the retention period
+pub fn prune_old_logs() {
+    let Ok(entries) = std::fs::read_dir(logs_dir()) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > LOG_RETENTION);
+        if expired {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
@@ -5,6 +5,8 @@
 //! - Config directory paths
 //! - Default shell detection
 //! - Process management (alive check, termination)
+//! - Terminal size and resize notifications
+//! - Process command lines and working directories (foreground process tracking)
 //!
 //! Supports Linux, macOS, and Windows.
 
@@ -185,6 +187,85 @@ pub fn extract_path_basename(path: &str) -> &str {
     path.rsplit(['/', '\\']).next().unwrap_or(path)
 }
 
+/// Size of the controlling terminal as (cols, rows), 80x24 if unknown
+pub fn terminal_size() -> (u16, u16) {
+    if let Some((w, h)) = term_size::dimensions() {
+        return (w as u16, h as u16);
+    }
+    // Default fallback
+    (80, 24)
+}
+
+/// Command line (argv) of a running process.
+///
+/// Read from `/proc/<pid>/cmdline`, so only available on Linux.
+pub fn process_command_line(pid: u32) -> Option<Vec<String>> {
+    #[cfg(target_os = "linux")]
+    {
+        let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        let argv: Vec<String> = raw
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        (!argv.is_empty()).then_some(argv)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Current working directory of a running process (Linux only)
+pub fn process_cwd(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_link(format!("/proc/{}/cwd", pid))
+            .ok()
+            .map(|p| p.display().to_string())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Local terminal window size changes (SIGWINCH)
+pub struct ResizeSignal {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl ResizeSignal {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = signal(SignalKind::window_change())
+                .map_err(|e| tracing::warn!("Failed to watch terminal resizes: {}", e))
+                .ok();
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    /// Wait for the next resize; never completes if resizes can't be watched
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = self.signal.as_mut() {
+            if signal.recv().await.is_some() {
+                return;
+            }
+        }
+        std::future::pending::<()>().await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -25,6 +25,10 @@ pub enum ClientMessage {
         raw: bool,
         #[serde(default)]
         client_msg_id: Option<String>,
+        /// Press Enter after the text, as a separate write a moment later
+        /// (TUIs take text and Enter in one write as a paste)
+        #[serde(default)]
+        submit: bool,
     },
     /// Resize PTY - mobile sends terminal dimensions
     PtyResize {
@@ -58,6 +62,114 @@ pub enum ClientMessage {
         #[serde(default)]
         max_bytes: Option<usize>,
     },
+    /// Download the asciicast recording of a session
+    ExportSession {
+        session_id: String,
+    },
+    /// Search session output (live sessions and persisted logs)
+    SearchSession {
+        pattern: String,
+        /// Session ID prefix or name; all sessions if omitted
+        #[serde(default)]
+        session_id: Option<String>,
+        /// Only match lines from the last N seconds
+        #[serde(default)]
+        since_secs: Option<u64>,
+        #[serde(default)]
+        case_insensitive: bool,
+        #[serde(default)]
+        max_results: Option<usize>,
+    },
+    /// Request readable text of a session (ANSI stripped, lines reconstructed)
+    GetTranscript {
+        session_id: String,
+        #[serde(default)]
+        last_lines: Option<usize>,
+    },
+    /// Choose how a shared session's PTY size is arbitrated
+    SetSizePolicy {
+        session_id: String,
+        policy: String, // "desktop_wins" | "smallest" | "active_client" | "fixed"
+        /// Size for the "fixed" policy
+        #[serde(default)]
+        cols: Option<u16>,
+        #[serde(default)]
+        rows: Option<u16>,
+    },
+    /// Daemon queue and memory statistics
+    GetDiagnostics,
+    /// Tune output batching for this connection (omitted fields keep their value)
+    SetBatching {
+        /// Longest output is held before sending; 0 sends every write immediately
+        #[serde(default)]
+        max_delay_ms: Option<u64>,
+        #[serde(default)]
+        max_bytes: Option<usize>,
+    },
+    /// Show a notification on every device (in-app and push), e.g. from scripts
+    SendNotification {
+        title: String,
+        #[serde(default)]
+        body: String,
+        /// Session the notification is about, if any
+        #[serde(default)]
+        session_id: Option<String>,
+        /// "normal" (default) | "high"
+        #[serde(default)]
+        priority: Option<String>,
+    },
+    /// Notify when commands running at least `after_secs` finish (0 = never)
+    SetCommandNotify {
+        /// Session to configure; the daemon-wide default if omitted
+        #[serde(default)]
+        session_id: Option<String>,
+        after_secs: u64,
+    },
+    /// Hold text until the session is waiting, then type it followed by Enter
+    QueueInput {
+        session_id: String,
+        text: String,
+        /// Wait type that triggers delivery; "awaiting_response" if omitted
+        #[serde(default)]
+        deliver_on: Option<String>,
+    },
+    /// Request a session's queued input
+    GetInputQueue {
+        session_id: String,
+    },
+    /// Replace the text of a queued input
+    EditQueuedInput {
+        session_id: String,
+        id: u64,
+        text: String,
+    },
+    /// Drop a queued input before it is delivered
+    CancelQueuedInput {
+        session_id: String,
+        id: u64,
+    },
+    /// Watch a session's output for a regex (see `trigger_matched`)
+    AddTrigger {
+        session_id: String,
+        pattern: String,
+        #[serde(default)]
+        name: Option<String>,
+        /// Also send a push notification when it fires
+        #[serde(default)]
+        push: bool,
+        /// Minimum seconds between two firings (default 60)
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+    },
+    /// Stop watching for a trigger added with `add_trigger`
+    RemoveTrigger {
+        session_id: String,
+        id: u64,
+    },
+    /// Request a session's triggers (answered with `triggers`)
+    GetTriggers {
+        session_id: String,
+    },
 }
 
 /// Messages sent from server to mobile client
@@ -88,6 +200,14 @@ pub enum ServerMessage {
         command: String,
         project_path: String,
         started_at: String,
+        /// Current directory, once known (launch directory is `project_path`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<String>,
+        /// Branch (or short commit when detached) if `cwd` is in a git repository
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        git_branch: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        git_dirty: Option<bool>,
     },
     /// List of available sessions
     Sessions {
@@ -130,6 +250,156 @@ pub enum ServerMessage {
         data: String, // base64 encoded
         total_bytes: usize,
     },
+    /// Current screen of a session, sent on subscribe so late joiners see
+    /// exactly what the desktop sees
+    ScreenSnapshot {
+        session_id: String,
+        cols: u16,
+        rows: u16,
+        cursor_row: u16,
+        cursor_col: u16,
+        cursor_visible: bool,
+        alternate_screen: bool,
+        application_cursor: bool,
+        application_keypad: bool,
+        bracketed_paste: bool,
+        title: String,
+        data: String, // base64 encoded re-rendered screen
+    },
+    /// Plain-text transcript of a session's scrollback
+    Transcript {
+        session_id: String,
+        lines: Vec<String>,
+        total_lines: usize,
+    },
+    /// Results of a SearchSession request (oldest first)
+    SearchResults {
+        pattern: String,
+        matches: Vec<SearchMatch>,
+        truncated: bool,
+    },
+    /// One chunk of a recording; chunks arrive in order until `done`
+    SessionExport {
+        session_id: String,
+        format: String, // "asciicast-v2"
+        filename: String,
+        data: String, // base64 encoded
+        /// Position of this chunk in the file
+        offset: usize,
+        total_bytes: usize,
+        done: bool,
+    },
+    /// Response to GetDiagnostics
+    Diagnostics {
+        clients: Vec<ClientDiagnostics>,
+        sessions: usize,
+        scrollback_bytes: usize,
+        scrollback_max_bytes: usize,
+        overflow_policy: String, // "resync" | "disconnect"
+    },
+    /// A session's queued input, sent on request and whenever it changes
+    InputQueue {
+        session_id: String,
+        items: Vec<QueuedInputItem>,
+    },
+    /// Reply to `add_trigger`: the new trigger's ID
+    TriggerAdded {
+        session_id: String,
+        id: u64,
+    },
+    /// A session's output triggers, sent on request and whenever they change
+    Triggers {
+        session_id: String,
+        items: Vec<TriggerItem>,
+    },
+    /// A trigger's pattern showed up in the session's output
+    TriggerMatched {
+        session_id: String,
+        trigger_id: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        pattern: String,
+        /// The matched text and the line it is on
+        matched: String,
+        line: String,
+        timestamp: String,
+    },
+    /// A command started at the session's shell prompt (shell integration)
+    CommandStarted {
+        session_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+        timestamp: String,
+    },
+    /// The command finished; `exit_code` is missing if the shell didn't report it
+    CommandFinished {
+        session_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        timestamp: String,
+        /// Ran longer than the notify threshold: show an in-app notification
+        #[serde(default)]
+        notify: bool,
+    },
+    /// Notification sent by a script (`mobilecli notify`) or another client
+    CustomNotification {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_id: Option<String>,
+        title: String,
+        body: String,
+        priority: String, // "normal" | "high"
+        timestamp: String,
+    },
+    /// A queued input was typed into the session
+    QueuedInputDelivered {
+        session_id: String,
+        id: u64,
+        text: String,
+        wait_type: String,
+    },
+}
+
+/// Input held until a session is waiting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedInputItem {
+    pub id: u64,
+    pub text: String,
+    pub deliver_on: String,
+    pub queued_at: String,
+}
+
+/// Regex watched for in a session's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerItem {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub pattern: String,
+    /// Only fires while the session runs this CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cli: Option<String>,
+    pub push: bool,
+    pub cooldown_secs: u64,
+    /// From the `triggers` setting rather than added by a client
+    pub from_config: bool,
+}
+
+/// Queue state of one connected mobile client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientDiagnostics {
+    pub address: String,
+    pub connected_at: String,
+    /// Control messages waiting to be sent
+    pub queued_messages: usize,
+    /// PTY output chunks waiting to be sent
+    pub queued_output: usize,
+    /// Output bytes held in batches
+    pub pending_batch_bytes: usize,
+    /// Times the client fell behind and was resynced or dropped
+    pub overflows: u64,
 }
 
 /// Session list item for GetSessions response
@@ -143,6 +413,51 @@ pub struct SessionListItem {
     pub started_at: String,
     /// Explicit CLI type identifier for mobile app disambiguation
     pub cli_type: String,
+    /// Bytes currently held in the session's scrollback buffer
+    #[serde(default)]
+    pub scrollback_bytes: usize,
+    /// Scrollback limit for this session
+    #[serde(default)]
+    pub scrollback_max_bytes: usize,
+    /// How the PTY size is arbitrated between desktop and phones
+    #[serde(default)]
+    pub size_policy: String,
+    /// Wait type if the session is waiting for input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_state: Option<String>,
+    /// Clients currently subscribed to the session
+    #[serde(default)]
+    pub viewers: usize,
+    /// Command line of the process in the foreground (Linux only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground_command: Option<String>,
+    /// Current directory (from shell integration or the foreground process)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Commands running at least this long notify when they finish (0 = never)
+    #[serde(default)]
+    pub notify_command_after_secs: u64,
+    /// Git branch of `cwd`, if it is in a repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    /// Uncommitted changes in that repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+}
+
+/// A matching output line returned by SearchSession
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub session_id: String,
+    pub session_name: String,
+    /// Line number in the session transcript (0 = first line of output)
+    pub line_number: usize,
+    /// PTY stream byte offset at which the line was complete
+    pub offset: u64,
+    pub timestamp: String,
+    pub line: String,
+    /// Still on the live screen rather than in the persisted log
+    pub live: bool,
 }
 
 /// Connection info for QR code / pairing
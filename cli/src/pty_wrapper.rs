@@ -7,14 +7,17 @@
 //! 4. Relays input from daemon (mobile) to the PTY
 //! 5. Handles terminal resize events
 
+use crate::batching::{self, BatchConfig, OutputBatcher};
 use crate::daemon::{get_port, DEFAULT_PORT};
+use crate::platform;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use colored::Colorize;
 use futures_util::{SinkExt, StreamExt};
-use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use std::io::{Read, Write};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
@@ -31,12 +34,106 @@ pub enum WrapError {
     DaemonConnection(String),
 }
 
+/// PTY output waiting to be sent to the daemon beyond which more is dropped
+/// (the daemon connection stalled); the local terminal is never held up
+const DAEMON_BACKLOG_MAX: usize = 4 * 1024 * 1024;
+
+/// How long the final messages may take to reach the daemon on exit
+const DAEMON_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Minimum gap between "local_active" reports while typing locally
+const LOCAL_ACTIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Environment exported to the wrapped command, so scripts inside a session
+/// can find it (`mobilecli rename`, `mobilecli notify`) and nesting is caught.
+/// The name is the one at launch and goes stale after a rename; scripts
+/// should identify the session by its ID.
+pub const SESSION_ID_ENV: &str = "MOBILECLI_SESSION_ID";
+pub const SESSION_NAME_ENV: &str = "MOBILECLI_SESSION_NAME";
+pub const PORT_ENV: &str = "MOBILECLI_PORT";
+
+/// How often the PTY's foreground process is checked
+const FOREGROUND_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Messages to the daemon, written by a separate task so a stalled daemon
+/// connection never blocks the session's event loop
+struct DaemonQueue {
+    tx: mpsc::UnboundedSender<(Message, usize)>,
+    /// PTY output bytes queued but not yet written to the socket
+    queued_output: Arc<AtomicUsize>,
+    dropped_output: usize,
+}
+
+impl DaemonQueue {
+    fn spawn<S>(mut ws_tx: S) -> (Self, tokio::task::JoinHandle<()>)
+    where
+        S: futures_util::Sink<Message> + Unpin + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<(Message, usize)>();
+        let queued_output = Arc::new(AtomicUsize::new(0));
+        let writer_queued = queued_output.clone();
+        let writer = tokio::spawn(async move {
+            while let Some((msg, output_bytes)) = rx.recv().await {
+                let sent = ws_tx.send(msg).await;
+                writer_queued.fetch_sub(output_bytes, Ordering::Relaxed);
+                if sent.is_err() {
+                    tracing::debug!("Failed to send to daemon");
+                    break;
+                }
+            }
+            let _ = ws_tx.close().await;
+        });
+        let queue = Self {
+            tx,
+            queued_output,
+            dropped_output: 0,
+        };
+        (queue, writer)
+    }
+
+    fn send(&self, msg: Message) {
+        let _ = self.tx.send((msg, 0));
+    }
+
+    /// Queue PTY output, or drop it while the daemon is too far behind.
+    /// Once output fits again the gap is reported ahead of it; returns true
+    /// then, so the caller can have the program redraw.
+    fn send_output(&mut self, data: &[u8]) -> bool {
+        if self.queued_output.load(Ordering::Relaxed) + data.len() > DAEMON_BACKLOG_MAX {
+            self.dropped_output += data.len();
+            tracing::debug!(
+                "Daemon not keeping up; dropped {} bytes of output",
+                self.dropped_output
+            );
+            return false;
+        }
+        let gap = self.dropped_output > 0;
+        if gap {
+            let msg = serde_json::json!({
+                "type": "output_gap",
+                "dropped_bytes": self.dropped_output,
+            });
+            self.send(Message::Text(msg.to_string()));
+            self.dropped_output = 0;
+        }
+        self.queued_output.fetch_add(data.len(), Ordering::Relaxed);
+        let _ = self.tx.send((output_message(data), data.len()));
+        gap
+    }
+}
+
 /// Configuration for running a wrapped command
 pub struct WrapConfig {
     pub command: String,
     pub args: Vec<String>,
     pub session_name: String,
     pub quiet: bool,
+    /// Ask the daemon to record this session (asciicast v2)
+    pub record: bool,
+    /// Scrollback size requested from the daemon (daemon default if None)
+    pub scrollback_bytes: Option<usize>,
+    /// Notify after commands running at least this long (daemon default if None)
+    pub notify_after_secs: Option<u64>,
 }
 
 /// Resolve a command to its full path
@@ -52,13 +149,75 @@ fn resolve_command(cmd: &str) -> Option<String> {
         .map(|p| p.to_string_lossy().to_string())
 }
 
-/// Get terminal size from the current terminal
-fn get_terminal_size() -> (u16, u16) {
-    if let Some((w, h)) = term_size::dimensions() {
-        return (w as u16, h as u16);
+/// PTY output frame for the daemon
+fn output_message(data: &[u8]) -> Message {
+    let msg = serde_json::json!({
+        "type": "pty_output",
+        "data": BASE64.encode(data),
+    });
+    Message::Text(msg.to_string())
+}
+
+/// Make full-screen programs repaint (after output was lost on the way to
+/// the daemon) by briefly changing the PTY size, which sends SIGWINCH
+fn force_redraw(master: &dyn MasterPty) {
+    if let Ok(size) = master.get_size() {
+        resize_pty(master, size.cols, size.rows.saturating_add(1));
+        resize_pty(master, size.cols, size.rows);
     }
-    // Default fallback
-    (80, 24)
+}
+
+/// Resize the PTY unless it already has this size; returns whether it changed
+fn resize_pty_if_changed(master: &dyn MasterPty, cols: u16, rows: u16) -> bool {
+    let unchanged = master
+        .get_size()
+        .is_ok_and(|size| size.cols == cols && size.rows == rows);
+    !unchanged && resize_pty(master, cols, rows)
+}
+
+/// Resize the PTY; returns whether the new size was applied
+fn resize_pty(master: &dyn MasterPty, cols: u16, rows: u16) -> bool {
+    master
+        .resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .is_ok()
+}
+
+/// The PTY's foreground process group leader
+#[derive(PartialEq)]
+struct Foreground {
+    pid: i32,
+    argv: Vec<String>,
+    cwd: Option<String>,
+}
+
+#[cfg(unix)]
+fn foreground_process(master: &dyn MasterPty) -> Option<Foreground> {
+    let pid = master.process_group_leader()?;
+    Some(Foreground {
+        pid,
+        argv: platform::process_command_line(pid as u32)?,
+        cwd: platform::process_cwd(pid as u32),
+    })
+}
+
+#[cfg(not(unix))]
+fn foreground_process(_master: &dyn MasterPty) -> Option<Foreground> {
+    None
+}
+
+/// Report the applied PTY size to the daemon (screen model, recordings, viewers)
+fn resized_message(cols: u16, rows: u16) -> Message {
+    let msg = serde_json::json!({
+        "type": "pty_resized",
+        "cols": cols,
+        "rows": rows,
+    });
+    Message::Text(msg.to_string())
 }
 
 /// Run a command wrapped with mobile streaming via daemon
@@ -84,6 +243,8 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
 
     let (mut ws_tx, mut ws_rx) = ws_stream.split();
 
+    let (cols, rows) = platform::terminal_size();
+
     // Register with daemon as a PTY session
     let register_msg = serde_json::json!({
         "type": "register_pty",
@@ -91,6 +252,11 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         "name": config.session_name,
         "command": config.command,
         "project_path": cwd,
+        "cols": cols,
+        "rows": rows,
+        "record": config.record,
+        "scrollback_bytes": config.scrollback_bytes,
+        "notify_after_secs": config.notify_after_secs,
     });
     ws_tx
         .send(Message::Text(register_msg.to_string()))
@@ -98,6 +264,8 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         .map_err(|e| WrapError::DaemonConnection(format!("Failed to register: {}", e)))?;
 
     // Wait for registration acknowledgment
+    let mut recording_path = None;
+    let mut desktop_decides = true;
     if let Some(Ok(Message::Text(text))) = ws_rx.next().await {
         if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&text) {
             if msg["type"].as_str() != Some("registered") {
@@ -105,6 +273,8 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
                     "Unexpected response from daemon".to_string(),
                 ));
             }
+            recording_path = msg["recording"].as_str().map(|s| s.to_string());
+            desktop_decides = msg["desktop_decides"].as_bool().unwrap_or(true);
         }
     }
 
@@ -119,11 +289,15 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
             )
             .dimmed()
         );
+        if let Some(path) = &recording_path {
+            println!("{} {}", "⏺ Recording to".red(), path.dimmed());
+        }
+    } else if config.record && recording_path.is_none() {
+        eprintln!("{}", "⚠ Daemon could not start the recording".yellow());
     }
 
     // Create PTY
     let pty_system = native_pty_system();
-    let (cols, rows) = get_terminal_size();
 
     let pair = pty_system
         .openpty(PtySize {
@@ -144,6 +318,11 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         "TERM",
         std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".to_string()),
     );
+    // Session identity; also marks the shell as already inside a session
+    cmd.env(SESSION_ID_ENV, &session_id);
+    cmd.env(SESSION_NAME_ENV, &config.session_name);
+    cmd.env(PORT_ENV, port.to_string());
+    cmd.env_remove(crate::shell_integration::AUTO_WRAP_ENV);
 
     // Spawn the command
     let mut child = pair
@@ -167,16 +346,20 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     let running = Arc::new(AtomicBool::new(true));
     let running_reader = running.clone();
 
-    // Channel for PTY output
+    // Channel for PTY output on its way to the daemon
     let (output_tx, mut output_rx) = mpsc::unbounded_channel::<Vec<u8>>();
 
-    // Spawn thread to read from PTY
+    // Spawn thread to read from PTY. It writes the local terminal itself, so
+    // local output never waits on the event loop or the daemon connection.
     let reader_handle = std::thread::spawn(move || {
         let mut buf = [0u8; 4096];
+        let mut stdout = std::io::stdout();
         while running_reader.load(Ordering::SeqCst) {
             match reader.read(&mut buf) {
                 Ok(0) => break, // EOF
                 Ok(n) => {
+                    let _ = stdout.write_all(&buf[..n]);
+                    let _ = stdout.flush();
                     let _ = output_tx.send(buf[..n].to_vec());
                 }
                 Err(e) => {
@@ -224,35 +407,96 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     });
 
     // Main event loop
-    let mut stdout = std::io::stdout();
     let mut exit_code: i32 = 0;
+    let (mut daemon, daemon_writer) = DaemonQueue::spawn(ws_tx);
+
+    // Small writes are coalesced before going to the daemon; the local
+    // terminal is always written immediately
+    let settings = crate::setup::load_settings();
+    let mut batcher = OutputBatcher::new(BatchConfig::new(
+        settings.output_batch_ms,
+        settings.output_batch_bytes,
+    ));
+
+    // The daemon arbitrates the PTY size between this terminal and mobile
+    // viewers; local resizes and typing are reported, and the size it picks
+    // comes back as a resize message. While it says the desktop decides, a
+    // local resize is applied right away instead of waiting for the answer.
+    let mut resize_signal = platform::ResizeSignal::new();
+    let mut last_local_active: Option<Instant> = None;
+
+    // Report what runs in the foreground (e.g. `claude` started from a
+    // shell) and where, so the daemon knows which CLI the session is showing
+    // and its current directory
+    let mut foreground_poll = tokio::time::interval(FOREGROUND_POLL_INTERVAL);
+    let mut last_foreground = None;
 
     loop {
         tokio::select! {
             // PTY output
             Some(data) = output_rx.recv() => {
-                // Write to local terminal
-                let _ = stdout.write_all(&data);
-                let _ = stdout.flush();
+                // Send to daemon (now, or once the batch is due)
+                if let Some(batch) = batcher.push(&data, Instant::now()) {
+                    if daemon.send_output(&batch) {
+                        force_redraw(&*master);
+                    }
+                }
+            }
 
-                // Send to daemon
-                let msg = serde_json::json!({
-                    "type": "pty_output",
-                    "data": BASE64.encode(&data),
-                });
-                if ws_tx.send(Message::Text(msg.to_string())).await.is_err() {
-                    tracing::debug!("Failed to send PTY output to daemon");
+            // Pending output batch is due
+            _ = batching::sleep_until(batcher.deadline()) => {
+                if let Some(batch) = batcher.flush() {
+                    if daemon.send_output(&batch) {
+                        force_redraw(&*master);
+                    }
                 }
             }
 
             // Local stdin input
             Some(input) = stdin_rx.recv() => {
+                let now = Instant::now();
+                if last_local_active.map_or(true, |t| now.duration_since(t) >= LOCAL_ACTIVE_INTERVAL) {
+                    last_local_active = Some(now);
+                    let msg = serde_json::json!({ "type": "local_active" });
+                    daemon.send(Message::Text(msg.to_string()));
+                }
                 if let Err(e) = writer.write_all(&input) {
                     tracing::debug!("Failed to write stdin to PTY: {}", e);
                 }
                 let _ = writer.flush();
             }
 
+            // Local terminal resized
+            _ = resize_signal.recv() => {
+                let (cols, rows) = platform::terminal_size();
+                if desktop_decides && resize_pty_if_changed(&*master, cols, rows) {
+                    daemon.send(resized_message(cols, rows));
+                }
+                let msg = serde_json::json!({
+                    "type": "local_resize",
+                    "cols": cols,
+                    "rows": rows,
+                });
+                daemon.send(Message::Text(msg.to_string()));
+            }
+
+            // Foreground process changed
+            _ = foreground_poll.tick() => {
+                let foreground = foreground_process(&*master);
+                if foreground.is_some() && foreground != last_foreground {
+                    if let Some(fg) = &foreground {
+                        let msg = serde_json::json!({
+                            "type": "foreground",
+                            "pid": fg.pid,
+                            "argv": fg.argv,
+                            "cwd": fg.cwd,
+                        });
+                        daemon.send(Message::Text(msg.to_string()));
+                    }
+                    last_foreground = foreground;
+                }
+            }
+
             // Messages from daemon (input/resize from mobile)
             result = ws_rx.next() => {
                 match result {
@@ -270,21 +514,22 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
                                     }
                                 }
                                 Some("resize") => {
+                                    if let Some(decides) = msg["desktop_decides"].as_bool() {
+                                        desktop_decides = decides;
+                                    }
                                     if let (Some(cols), Some(rows)) = (
                                         msg["cols"].as_u64(),
                                         msg["rows"].as_u64(),
                                     ) {
+                                        // (0, 0) means the local terminal's size
                                         let (cols, rows) = if cols == 0 || rows == 0 {
-                                            get_terminal_size()
+                                            platform::terminal_size()
                                         } else {
                                             (cols as u16, rows as u16)
                                         };
-                                        let _ = master.resize(PtySize {
-                                            rows,
-                                            cols,
-                                            pixel_width: 0,
-                                            pixel_height: 0,
-                                        });
+                                        if resize_pty_if_changed(&*master, cols, rows) {
+                                            daemon.send(resized_message(cols, rows));
+                                        }
                                     }
                                 }
                                 _ => {}
@@ -322,15 +567,25 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     // Cleanup
     running.store(false, Ordering::SeqCst);
 
-    // Notify daemon that the session ended (so mobile closes it promptly)
+    // Send any batched output, then notify daemon that the session ended
+    // (so mobile closes it promptly)
+    while let Ok(data) = output_rx.try_recv() {
+        if let Some(batch) = batcher.push(&data, Instant::now()) {
+            daemon.send_output(&batch);
+        }
+    }
+    if let Some(batch) = batcher.flush() {
+        daemon.send_output(&batch);
+    }
     let msg = serde_json::json!({
         "type": "session_ended",
         "exit_code": exit_code,
     });
-    let _ = ws_tx.send(Message::Text(msg.to_string())).await;
+    daemon.send(Message::Text(msg.to_string()));
 
-    // Close WebSocket
-    let _ = ws_tx.close().await;
+    // Let the writer finish and close the WebSocket, unless the daemon is stuck
+    drop(daemon);
+    let _ = tokio::time::timeout(DAEMON_CLOSE_TIMEOUT, daemon_writer).await;
 
     // Wait for reader thread
     let _ = reader_handle.join();
@@ -0,0 +1,208 @@
+//! Asciicast v2 session recording
+//!
+//! Writes PTY output and resize events to `~/.mobilecli/recordings/<session_id>.cast`
+//! in the asciinema v2 format, so runs can be replayed with `asciinema play`
+//! or shared with teammates. Events are written by a background thread so
+//! disk I/O never blocks the session's task.
+
+use crate::platform;
+use crate::session;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Recordings larger than this are not exported
+pub const MAX_EXPORT_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Size of each `session_export` chunk, before base64
+pub const EXPORT_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Longest buffered events wait before being flushed to disk; exports of live
+/// sessions may miss at most this much output
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Recordings directory path (cross-platform)
+pub fn recordings_dir() -> PathBuf {
+    platform::config_dir().join("recordings")
+}
+
+/// Path of the recording for a session
+pub fn recording_path(session_id: &str) -> PathBuf {
+    recordings_dir().join(format!("{}.cast", session_id))
+}
+
+/// Metadata written to the asciicast header line
+pub struct CastHeader<'a> {
+    pub cols: u16,
+    pub rows: u16,
+    pub title: &'a str,
+    pub command: &'a str,
+}
+
+/// Incremental asciicast v2 writer for a single session
+pub struct CastRecorder {
+    /// Event lines for the writer thread
+    events: mpsc::Sender<String>,
+    started: Instant,
+    /// Trailing bytes of an incomplete UTF-8 sequence split across PTY reads
+    pending_utf8: Vec<u8>,
+    path: PathBuf,
+}
+
+impl CastRecorder {
+    /// Create the recording file for a session and write the header
+    pub fn create(session_id: &str, header: CastHeader<'_>) -> io::Result<Self> {
+        if !session::is_valid_session_id(session_id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid session id",
+            ));
+        }
+        std::fs::create_dir_all(recordings_dir())?;
+        let path = recording_path(session_id);
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        let header = serde_json::json!({
+            "version": 2,
+            "width": header.cols,
+            "height": header.rows,
+            "timestamp": chrono::Utc::now().timestamp(),
+            "title": header.title,
+            "command": header.command,
+            "env": {
+                "TERM": std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".to_string()),
+                "SHELL": platform::default_shell(),
+            },
+        });
+        writeln!(writer, "{}", header)?;
+        writer.flush()?;
+
+        let (events, rx) = mpsc::channel();
+        std::thread::spawn(move || write_events(writer, rx));
+
+        Ok(Self {
+            events,
+            started: Instant::now(),
+            pending_utf8: Vec::new(),
+            path,
+        })
+    }
+
+    /// Path of the file being written
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Record a chunk of PTY output
+    pub fn write_output(&mut self, data: &[u8]) -> io::Result<()> {
+        self.pending_utf8.extend_from_slice(data);
+        let text = take_complete_utf8(&mut self.pending_utf8);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.write_event("o", &text)
+    }
+
+    /// Record a terminal resize
+    pub fn write_resize(&mut self, cols: u16, rows: u16) -> io::Result<()> {
+        self.write_event("r", &format!("{}x{}", cols, rows))
+    }
+
+    fn write_event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let event = serde_json::json!([(elapsed * 1_000_000.0).round() / 1_000_000.0, code, data]);
+        // The writer thread only goes away after a write error
+        self.events
+            .send(event.to_string())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "recording writer stopped"))
+    }
+}
+
+/// Writer thread: append event lines, flushing once output pauses or
+/// `FLUSH_INTERVAL` has passed. Ends when the recorder is dropped or a write fails.
+fn write_events(mut writer: BufWriter<File>, rx: mpsc::Receiver<String>) {
+    let mut last_flush = Instant::now();
+    loop {
+        let result = match rx.recv_timeout(FLUSH_INTERVAL) {
+            Ok(event) => writeln!(writer, "{}", event).and_then(|_| {
+                if last_flush.elapsed() >= FLUSH_INTERVAL {
+                    last_flush = Instant::now();
+                    writer.flush()
+                } else {
+                    Ok(())
+                }
+            }),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                last_flush = Instant::now();
+                writer.flush()
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let _ = writer.flush();
+                return;
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Recording write failed: {}", e);
+            return;
+        }
+    }
+}
+
+/// Drain the longest valid UTF-8 prefix from `buf`, keeping an incomplete
+/// trailing sequence for the next chunk. Invalid bytes are replaced lossily.
+pub fn take_complete_utf8(buf: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(buf) {
+        Ok(_) => buf.len(),
+        // error_len() == None means the input ended mid-sequence
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => buf.len(),
+    };
+    let text = String::from_utf8_lossy(&buf[..complete]).to_string();
+    buf.drain(..complete);
+    text
+}
+
+/// Open a finished or in-progress recording for export; returns its size
+pub async fn open_recording(session_id: &str) -> io::Result<(tokio::fs::File, u64)> {
+    if !session::is_valid_session_id(session_id) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid session id",
+        ));
+    }
+    let file = tokio::fs::File::open(recording_path(session_id)).await?;
+    let len = file.metadata().await?.len();
+    if len > MAX_EXPORT_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Recording is too large to export ({} bytes)", len),
+        ));
+    }
+    Ok((file, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_complete_utf8_keeps_split_sequence() {
+        // "é" is 0xC3 0xA9; split it across two chunks
+        let mut buf = b"caf\xC3".to_vec();
+        assert_eq!(take_complete_utf8(&mut buf), "caf");
+        assert_eq!(buf, vec![0xC3]);
+
+        buf.extend_from_slice(b"\xA9!");
+        assert_eq!(take_complete_utf8(&mut buf), "é!");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_take_complete_utf8_replaces_invalid_bytes() {
+        let mut buf = b"a\xFFb".to_vec();
+        assert_eq!(take_complete_utf8(&mut buf), "a\u{FFFD}b");
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Server-side terminal emulation
+//!
+//! The daemon feeds every session's PTY output through a VT parser so that
+//! late-joining clients can be sent the current screen (including alternate
+//! screen, cursor and input modes) instead of a raw byte tail that may start
+//! in the middle of an escape sequence.
+
+/// Parsed terminal screen for one session
+pub struct ScreenState {
+    parser: vt100::Parser,
+}
+
+/// Point-in-time copy of a session's screen
+pub struct Snapshot {
+    pub cols: u16,
+    pub rows: u16,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    pub alternate_screen: bool,
+    pub application_cursor: bool,
+    pub application_keypad: bool,
+    pub bracketed_paste: bool,
+    pub title: String,
+    /// Escape sequences that reproduce the screen on a freshly reset terminal
+    pub data: Vec<u8>,
+}
+
+impl ScreenState {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            parser: vt100::Parser::new(rows.max(1), cols.max(1), 0),
+        }
+    }
+
+    /// Feed PTY output into the emulator
+    pub fn process(&mut self, bytes: &[u8]) {
+        self.parser.process(bytes);
+    }
+
+    /// Track the PTY size so line wrapping matches the real terminal
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        if cols > 0 && rows > 0 {
+            self.parser.set_size(rows, cols);
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        let (rows, cols) = self.parser.screen().size();
+        (cols, rows)
+    }
+
+    /// Capture the current screen
+    pub fn snapshot(&self) -> Snapshot {
+        let screen = self.parser.screen();
+        let (rows, cols) = screen.size();
+        let (cursor_row, cursor_col) = screen.cursor_position();
+
+        // Reset the client terminal first, then switch buffers so full-screen
+        // TUIs keep the primary screen intact when they exit.
+        let mut data = b"\x1b[!p\x1b[?1049l".to_vec();
+        if screen.alternate_screen() {
+            data.extend_from_slice(b"\x1b[?1049h");
+        }
+        data.extend_from_slice(&screen.state_formatted());
+        data.extend_from_slice(&screen.cursor_state_formatted());
+
+        Snapshot {
+            cols,
+            rows,
+            cursor_row,
+            cursor_col,
+            cursor_visible: !screen.hide_cursor(),
+            alternate_screen: screen.alternate_screen(),
+            application_cursor: screen.application_cursor(),
+            application_keypad: screen.application_keypad(),
+            bracketed_paste: screen.bracketed_paste(),
+            title: screen.title().to_string(),
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_tracks_alternate_screen() {
+        let mut screen = ScreenState::new(20, 5);
+        screen.process(b"shell prompt$ ");
+        screen.process(b"\x1b[?1049h\x1b[2J\x1b[HTUI");
+
+        let snap = screen.snapshot();
+        assert!(snap.alternate_screen);
+        assert_eq!((snap.cursor_row, snap.cursor_col), (0, 3));
+
+        // Replaying the snapshot on a fresh terminal reproduces the screen
+        let mut replay = ScreenState::new(20, 5);
+        replay.process(&snap.data);
+        assert!(replay.parser.screen().alternate_screen());
+        assert_eq!(
+            replay.parser.screen().contents(),
+            screen.parser.screen().contents()
+        );
+    }
+
+    #[test]
+    fn test_split_escape_sequence_is_not_rendered() {
+        let mut screen = ScreenState::new(20, 5);
+        screen.process(b"hello \x1b[3");
+        screen.process(b"1mred\x1b[0m");
+        assert_eq!(screen.parser.screen().contents(), "hello red");
+    }
+}
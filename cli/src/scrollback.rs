@@ -0,0 +1,172 @@
+//! Per-session scrollback buffer
+//!
+//! PTY output is kept as a ring of shared `Bytes` chunks: appending and
+//! evicting are O(1) per chunk instead of per byte, and history reads hand
+//! out reference-counted slices so the daemon lock is only held long enough
+//! to clone a few pointers.
+//!
+//! Small writes (keystroke echo, prompts) are coalesced into a pending
+//! buffer so the ring doesn't fill up with one-byte chunks.
+
+use bytes::{Buf, Bytes, BytesMut};
+use std::collections::VecDeque;
+
+/// Writes smaller than this are coalesced before becoming a chunk
+const COALESCE_BYTES: usize = 4096;
+
+pub struct Scrollback {
+    /// Sealed chunks, oldest first
+    chunks: VecDeque<Bytes>,
+    /// Small writes not yet sealed into a chunk (always newest)
+    pending: BytesMut,
+    len: usize,
+    max_bytes: usize,
+}
+
+impl Scrollback {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            pending: BytesMut::new(),
+            len: 0,
+            max_bytes,
+        }
+    }
+
+    /// Bytes currently held
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Append output, evicting the oldest bytes beyond `max_bytes`
+    pub fn push(&mut self, data: Bytes) {
+        if data.is_empty() {
+            return;
+        }
+        self.len += data.len();
+        if data.len() >= COALESCE_BYTES {
+            self.seal_pending();
+            self.chunks.push_back(data);
+        } else {
+            self.pending.extend_from_slice(&data);
+            if self.pending.len() >= COALESCE_BYTES {
+                self.seal_pending();
+            }
+        }
+        if self.len > self.max_bytes {
+            self.evict_front(self.len - self.max_bytes);
+        }
+    }
+
+    fn seal_pending(&mut self) {
+        if !self.pending.is_empty() {
+            self.chunks.push_back(self.pending.split().freeze());
+        }
+    }
+
+    /// Drop the oldest `n` bytes
+    pub fn evict_front(&mut self, mut n: usize) {
+        n = n.min(self.len);
+        self.len -= n;
+        while n > 0 {
+            let Some(front) = self.chunks.front_mut() else {
+                self.pending.advance(n);
+                return;
+            };
+            if front.len() <= n {
+                n -= front.len();
+                self.chunks.pop_front();
+            } else {
+                front.advance(n);
+                return;
+            }
+        }
+    }
+
+    /// The newest `max` bytes as shared chunks, oldest first. Only the
+    /// pending (small-write) buffer is copied.
+    pub fn tail(&self, max: usize) -> Vec<Bytes> {
+        let mut remaining = max.min(self.len);
+        let mut out = Vec::new();
+
+        if remaining > 0 && !self.pending.is_empty() {
+            let take = remaining.min(self.pending.len());
+            out.push(Bytes::copy_from_slice(
+                &self.pending[self.pending.len() - take..],
+            ));
+            remaining -= take;
+        }
+        for chunk in self.chunks.iter().rev() {
+            if remaining == 0 {
+                break;
+            }
+            let take = remaining.min(chunk.len());
+            out.push(chunk.slice(chunk.len() - take..));
+            remaining -= take;
+        }
+
+        out.reverse();
+        out
+    }
+
+    /// All held bytes as one contiguous buffer
+    #[cfg(test)]
+    pub fn to_vec(&self) -> Vec<u8> {
+        concat(&self.tail(self.len))
+    }
+}
+
+/// Join chunks returned by `Scrollback::tail`
+pub fn concat(chunks: &[Bytes]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunks.iter().map(|c| c.len()).sum());
+    for chunk in chunks {
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_oldest_bytes() {
+        let mut sb = Scrollback::new(10 * 1024);
+        for i in 0..8u8 {
+            sb.push(Bytes::from(vec![i; 2048]));
+        }
+        assert_eq!(sb.len(), 10 * 1024);
+        let data = sb.to_vec();
+        assert_eq!(data.len(), 10 * 1024);
+        // 16KB written, oldest 6KB dropped: starts in the middle of chunk 3
+        assert_eq!(data[0], 3);
+        assert_eq!(data[data.len() - 1], 7);
+    }
+
+    #[test]
+    fn test_small_writes_are_coalesced() {
+        let mut sb = Scrollback::new(1024 * 1024);
+        for _ in 0..10_000 {
+            sb.push(Bytes::from_static(b"x"));
+        }
+        assert_eq!(sb.len(), 10_000);
+        assert!(sb.chunks.len() <= 10_000 / COALESCE_BYTES);
+        assert_eq!(sb.to_vec(), vec![b'x'; 10_000]);
+    }
+
+    #[test]
+    fn test_tail_keeps_order_across_pending() {
+        let mut sb = Scrollback::new(1024 * 1024);
+        sb.push(Bytes::from(vec![b'a'; COALESCE_BYTES]));
+        sb.push(Bytes::from_static(b"bcd"));
+        assert_eq!(concat(&sb.tail(5)), b"aabcd");
+        assert_eq!(concat(&sb.tail(2)), b"cd");
+
+        sb.evict_front(COALESCE_BYTES + 1);
+        assert_eq!(sb.to_vec(), b"cd");
+    }
+}
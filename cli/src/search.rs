@@ -0,0 +1,183 @@
+//! Full-text search across session output
+//!
+//! Searches the persisted session logs plus the lines still being rendered
+//! for live sessions, so matches are found well beyond the scrollback window.
+
+use crate::output_log;
+use crate::protocol::SearchMatch;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+/// Default cap on returned matches
+pub const DEFAULT_MAX_RESULTS: usize = 200;
+
+/// Live (not yet persisted) lines of a running session
+pub struct LiveLines {
+    pub session_id: String,
+    pub name: String,
+    /// Transcript line number of `lines[0]`
+    pub first_line_number: usize,
+    pub offset: u64,
+    pub lines: Vec<String>,
+}
+
+pub struct SearchQuery {
+    pub regex: Regex,
+    /// Session ID prefix or name filter
+    pub session: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub max_results: usize,
+}
+
+impl SearchQuery {
+    fn matches_session(&self, session_id: &str, name: &str) -> bool {
+        match &self.session {
+            Some(filter) => {
+                session_id.starts_with(filter.as_str())
+                    || name.to_lowercase().contains(&filter.to_lowercase())
+            }
+            None => true,
+        }
+    }
+}
+
+/// Run a search over logs on disk and live lines. Returns the matches
+/// (oldest first) and whether the result was truncated.
+pub fn run(query: &SearchQuery, live: &[LiveLines]) -> (Vec<SearchMatch>, bool) {
+    let mut matches = search_logs(query);
+    matches.extend(search_live(query, live));
+
+    matches.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    let truncated = matches.len() > query.max_results;
+    if truncated {
+        // Keep the most recent matches
+        matches.drain(..matches.len() - query.max_results);
+    }
+    (matches, truncated)
+}
+
+/// Matches in the logs on disk
+fn search_logs(query: &SearchQuery) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for session_id in output_log::logged_sessions() {
+        let Ok((header, lines)) = output_log::read_log(&session_id) else {
+            continue;
+        };
+        if !query.matches_session(&header.session_id, &header.name) {
+            continue;
+        }
+        for line in lines {
+            if query.since.is_some_and(|since| line.timestamp < since) {
+                continue;
+            }
+            if query.regex.is_match(&line.text) {
+                matches.push(SearchMatch {
+                    session_id: header.session_id.clone(),
+                    session_name: header.name.clone(),
+                    line_number: line.line_number,
+                    offset: line.offset,
+                    timestamp: line.timestamp.to_rfc3339(),
+                    line: line.text,
+                    live: false,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Matches in the live lines. These are newer than anything persisted, so
+/// they pass any `since`.
+fn search_live(query: &SearchQuery, live: &[LiveLines]) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    let now = Utc::now().to_rfc3339();
+    for session in live {
+        if !query.matches_session(&session.session_id, &session.name) {
+            continue;
+        }
+        for (i, line) in session.lines.iter().enumerate() {
+            if query.regex.is_match(line) {
+                matches.push(SearchMatch {
+                    session_id: session.session_id.clone(),
+                    session_name: session.name.clone(),
+                    line_number: session.first_line_number + i,
+                    offset: session.offset,
+                    timestamp: now.clone(),
+                    line: line.clone(),
+                    live: true,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Parse a duration like `90s`, `30m`, `2h` or `1d` (bare numbers are seconds)
+pub fn parse_duration(input: &str) -> Option<std::time::Duration> {
+    let input = input.trim();
+    let (number, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => input.split_at(idx),
+        None => (input, "s"),
+    };
+    let value: u64 = number.parse().ok()?;
+    let unit_secs = match unit {
+        "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hr" | "hrs" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        _ => return None,
+    };
+    let secs = value.checked_mul(unit_secs)?;
+    Some(std::time::Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").map(|d| d.as_secs()), Some(90));
+        assert_eq!(parse_duration("30m").map(|d| d.as_secs()), Some(1800));
+        assert_eq!(parse_duration("2h").map(|d| d.as_secs()), Some(7200));
+        assert_eq!(parse_duration("1d").map(|d| d.as_secs()), Some(86400));
+        assert_eq!(parse_duration("2w"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("18446744073709551615d"), None);
+    }
+
+    #[test]
+    fn test_live_lines_search() {
+        let query = SearchQuery {
+            regex: Regex::new("panicked at").unwrap(),
+            session: Some("work".to_string()),
+            since: None,
+            max_results: 10,
+        };
+        let live = vec![
+            LiveLines {
+                session_id: "live-session-a".to_string(),
+                name: "Work".to_string(),
+                first_line_number: 40,
+                offset: 1234,
+                lines: vec![
+                    "running tests".to_string(),
+                    "thread 'main' panicked at src/lib.rs:10".to_string(),
+                ],
+            },
+            LiveLines {
+                session_id: "live-session-b".to_string(),
+                name: "Other".to_string(),
+                first_line_number: 0,
+                offset: 0,
+                lines: vec!["panicked at elsewhere".to_string()],
+            },
+        ];
+
+        let matches = search_live(&query, &live);
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].live);
+        assert_eq!(matches[0].line_number, 41);
+        assert_eq!(matches[0].session_id, "live-session-a");
+    }
+}
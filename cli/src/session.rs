@@ -19,6 +19,22 @@ pub struct SessionInfo {
     pub ws_port: u16,
     pub pid: u32,
     pub started_at: DateTime<Utc>,
+    /// Current directory and its git state, once known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+}
+
+/// Session IDs end up in file names (recordings, logs); only accept the
+/// characters the wrapper generates
+pub fn is_valid_session_id(session_id: &str) -> bool {
+    !session_id.is_empty()
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
 }
 
 /// Get the sessions file path (cross-platform)
@@ -169,3 +185,15 @@ pub fn list_active_sessions() -> Vec<SessionInfo> {
         .filter(|s| is_process_alive(s.pid))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_id_validation() {
+        assert!(is_valid_session_id("3f2a9c1e-8b7"));
+        assert!(!is_valid_session_id(""));
+        assert!(!is_valid_session_id("../config"));
+    }
+}
@@ -3,7 +3,10 @@
 //! Handles first-time setup and connection configuration.
 
 use crate::platform;
+use crate::sizing::SizePolicy;
+use crate::triggers::TriggerRule;
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
 use std::process::Command;
 
@@ -26,6 +29,86 @@ pub struct Config {
     pub connection_mode: ConnectionMode,
     pub tailscale_ip: Option<String>,
     pub local_ip: Option<String>,
+    /// Behaviour settings (stored as top-level keys in config.json)
+    pub settings: Settings,
+}
+
+/// User-tunable behaviour settings
+///
+/// Every field has a default so older config files keep working and
+/// users only need to add the keys they want to change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Record every session as an asciicast file (same as `mobilecli --record`)
+    pub record_sessions: bool,
+    /// Keep plain-text session output in `~/.mobilecli/logs` for
+    /// `mobilecli search` (off: only what is on live sessions' screens)
+    pub output_logs: bool,
+    /// Default scrollback kept per session, in bytes (`mobilecli --scrollback`)
+    pub scrollback_bytes: usize,
+    /// Upper bound on scrollback memory across all sessions, in bytes
+    pub scrollback_total_max_bytes: usize,
+    /// Longest PTY output is held to batch small writes (0 disables batching)
+    pub output_batch_ms: u64,
+    /// Send a batch as soon as it reaches this many bytes
+    pub output_batch_bytes: usize,
+    /// What to do with a mobile client whose queue overflows
+    pub slow_client_policy: OverflowPolicy,
+    /// How a shared session's PTY size is chosen (desktop vs. phones)
+    pub size_policy: SizePolicy,
+    /// Prefix key for `mobilecli link` commands, e.g. "C-]" or "C-a"
+    pub link_prefix: String,
+    /// Notify when a command that ran at least this many seconds finishes
+    /// (needs shell integration; 0 disables, `mobilecli --notify-after` per session)
+    pub notify_command_after_secs: u64,
+    /// `mobilecli init --auto` also wraps shells started over SSH
+    pub auto_wrap_ssh: bool,
+    /// `mobilecli init --auto` also wraps IDE terminals (VS Code, JetBrains, Emacs)
+    pub auto_wrap_ide: bool,
+    /// Regexes watched for in every session's output (`mobilecli watch-for`
+    /// adds them to a single session)
+    pub triggers: Vec<TriggerRule>,
+}
+
+/// Handling of mobile clients that can't keep up with output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the backlog and send fresh screen snapshots
+    #[default]
+    Resync,
+    /// Close the connection; the app reconnects and starts over
+    Disconnect,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::Resync => "resync",
+            OverflowPolicy::Disconnect => "disconnect",
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            record_sessions: false,
+            output_logs: true,
+            scrollback_bytes: 64 * 1024,
+            scrollback_total_max_bytes: 64 * 1024 * 1024,
+            output_batch_ms: 16,
+            output_batch_bytes: 32 * 1024,
+            slow_client_policy: OverflowPolicy::Resync,
+            size_policy: SizePolicy::ActiveClient,
+            link_prefix: crate::link::DEFAULT_PREFIX.to_string(),
+            notify_command_after_secs: 0,
+            auto_wrap_ssh: false,
+            auto_wrap_ide: false,
+            triggers: Vec::new(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -36,6 +119,7 @@ impl Default for Config {
             connection_mode: ConnectionMode::Local,
             tailscale_ip: None,
             local_ip: None,
+            settings: Settings::default(),
         }
     }
 }
@@ -106,9 +190,43 @@ pub fn load_config() -> Option<Config> {
             .get("local_ip")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string()),
+        settings: parse_settings(&json),
     })
 }
 
+/// Settings from the config JSON. A key with an invalid value falls back to
+/// its default with a warning; the other settings are still used.
+fn parse_settings(json: &serde_json::Value) -> Settings {
+    if let Ok(settings) = serde_json::from_value(json.clone()) {
+        return settings;
+    }
+    let Ok(serde_json::Value::Object(mut merged)) = serde_json::to_value(Settings::default())
+    else {
+        return Settings::default();
+    };
+    let keys: Vec<String> = merged.keys().cloned().collect();
+    for key in keys {
+        let Some(value) = json.get(&key) else {
+            continue;
+        };
+        let default = merged.insert(key.clone(), value.clone());
+        if let Err(e) =
+            serde_json::from_value::<Settings>(serde_json::Value::Object(merged.clone()))
+        {
+            tracing::warn!("Ignoring invalid '{}' in config.json: {}", key, e);
+            if let Some(default) = default {
+                merged.insert(key, default);
+            }
+        }
+    }
+    serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_default()
+}
+
+/// Load behaviour settings (defaults if no config exists yet)
+pub fn load_settings() -> Settings {
+    load_config().map(|c| c.settings).unwrap_or_default()
+}
+
 /// Save configuration
 pub fn save_config(config: &Config) -> io::Result<()> {
     let config_path = get_config_path();
@@ -122,13 +240,32 @@ pub fn save_config(config: &Config) -> io::Result<()> {
         ConnectionMode::Custom(url) => format!("custom:{}", url),
     };
 
-    let json = serde_json::json!({
+    let mut json = serde_json::json!({
         "device_id": config.device_id,
         "device_name": config.device_name,
         "connection_mode": mode_str,
         "tailscale_ip": config.tailscale_ip,
         "local_ip": config.local_ip,
     });
+    if let (Some(obj), Ok(serde_json::Value::Object(settings))) =
+        (json.as_object_mut(), serde_json::to_value(&config.settings))
+    {
+        // Keep settings the way the user wrote them unless they changed, so
+        // an invalid value isn't replaced by the default it was loaded as
+        let existing: Option<serde_json::Value> = std::fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        let loaded = existing
+            .as_ref()
+            .and_then(|json| serde_json::to_value(parse_settings(json)).ok());
+        for (key, value) in settings {
+            let unchanged = loaded.as_ref().and_then(|l| l.get(&key)) == Some(&value);
+            match existing.as_ref().and_then(|e| e.get(&key)) {
+                Some(raw) if unchanged => obj.insert(key, raw.clone()),
+                _ => obj.insert(key, value),
+            };
+        }
+    }
 
     std::fs::write(&config_path, serde_json::to_string_pretty(&json)?)?;
     Ok(())
@@ -393,6 +530,10 @@ pub fn run_setup_wizard() -> io::Result<Config> {
     };
 
     let mut config = Config::default();
+    // Keep behaviour settings across re-runs of the wizard
+    if let Some(existing) = load_config() {
+        config.settings = existing.settings;
+    }
 
     match choice {
         1 => {
@@ -569,3 +710,23 @@ pub fn get_connection_ip(config: &Config) -> Option<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_setting_keeps_the_others() {
+        let json = serde_json::json!({
+            "record_sessions": true,
+            "size_policy": "biggest",
+            "scrollback_bytes": 1024,
+            "triggers": [{ "name": "no pattern" }],
+        });
+        let settings = parse_settings(&json);
+        assert!(settings.record_sessions);
+        assert_eq!(settings.scrollback_bytes, 1024);
+        assert_eq!(settings.size_policy, SizePolicy::ActiveClient);
+        assert!(settings.triggers.is_empty());
+    }
+}
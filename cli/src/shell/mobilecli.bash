@@ -0,0 +1,75 @@
+# mobilecli shell integration for bash
+# Add to ~/.bashrc:  eval "$(mobilecli init bash)"
+#
+# Marks prompts and commands with OSC 133 and reports the current directory
+# with OSC 7, so MobileCLI knows exactly when commands start and finish.
+
+if [[ $- == *i* && -z ${__mobilecli_hooked-} ]]; then
+    __mobilecli_hooked=1
+    __mobilecli_ready=0
+    __mobilecli_running=0
+
+    __mobilecli_urlencode() {
+        local LC_ALL=C s=$1 out= c i
+        for ((i = 0; i < ${#s}; i++)); do
+            c=${s:i:1}
+            case $c in
+                [a-zA-Z0-9.~_/-]) out+=$c ;;
+                *) printf -v c '%%%02X' "'$c"; out+=$c ;;
+            esac
+        done
+        printf '%s' "$out"
+    }
+
+    # DEBUG trap: runs before every simple command; only the first one
+    # after a prompt starts a new command line. Our own prompt hooks don't
+    # count, so an empty command line reports nothing.
+    __mobilecli_preexec() {
+        case $BASH_COMMAND in
+            __mobilecli_precmd | __mobilecli_prompt_ready) return 0 ;;
+        esac
+        [[ $__mobilecli_ready == 1 && -z ${COMP_LINE-} ]] || return 0
+        __mobilecli_ready=0
+        __mobilecli_running=1
+        local cmd
+        cmd=$(HISTTIMEFORMAT= builtin history 1)
+        cmd=${cmd#*[0-9] }
+        printf '\e]133;C;cmdline_url=%s\a' "$(__mobilecli_urlencode "${cmd# }")"
+    }
+
+    __mobilecli_precmd() {
+        local status=$?
+        # Other PROMPT_COMMAND hooks aren't commands either
+        __mobilecli_ready=0
+        if [[ $__mobilecli_running == 1 ]]; then
+            printf '\e]133;D;%s\a' "$status"
+        fi
+        __mobilecli_running=0
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$(__mobilecli_urlencode "$PWD")"
+        printf '\e]133;A\a'
+    }
+
+    __mobilecli_prompt_ready() {
+        __mobilecli_ready=1
+    }
+
+    # Keep a DEBUG trap the user already has, running it after ours with
+    # the command's $? (its status still decides under extdebug)
+    __mobilecli_user_debug=$(trap -p DEBUG)
+    __mobilecli_user_debug=${__mobilecli_user_debug#"trap -- '"}
+    __mobilecli_user_debug=${__mobilecli_user_debug%"' DEBUG"}
+    __mobilecli_user_debug=${__mobilecli_user_debug//"'\\''"/"'"}
+
+    __mobilecli_debug() {
+        local ret=$?
+        __mobilecli_preexec
+        if [[ -n $__mobilecli_user_debug ]]; then
+            (exit $ret)
+            eval "$__mobilecli_user_debug"
+        fi
+    }
+
+    trap '__mobilecli_debug' DEBUG
+    PROMPT_COMMAND="__mobilecli_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __mobilecli_prompt_ready"
+    PS1="$PS1\[\e]133;B\a\]"
+fi
@@ -0,0 +1,22 @@
+# mobilecli shell integration for fish
+# Add to ~/.config/fish/config.fish:  mobilecli init fish | source
+#
+# Marks prompts and commands with OSC 133 and reports the current directory
+# with OSC 7, so MobileCLI knows exactly when commands start and finish.
+
+if status is-interactive; and not set -q __mobilecli_hooked
+    set -g __mobilecli_hooked 1
+
+    function __mobilecli_preexec --on-event fish_preexec
+        printf '\e]133;C;cmdline_url=%s\a' (string escape --style=url -- $argv[1])
+    end
+
+    function __mobilecli_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    function __mobilecli_prompt --on-event fish_prompt
+        printf '\e]7;file://%s%s\a' (hostname) (string escape --style=url -- $PWD)
+        printf '\e]133;A\a'
+    end
+end
@@ -0,0 +1,346 @@
+//! Shell integration escape sequences in PTY output
+//!
+//! Shells configured for it report state through OSC sequences that
+//! terminals otherwise ignore: OSC 7 carries the current directory as a
+//! `file://host/path` URL after every prompt (ignored when the host is
+//! another machine, e.g. a shell over ssh), and OSC 133 marks where
+//! prompts, commands and their output begin and end (`A` prompt, `B`
+//! input, `C` output with an optional `cmdline_url=`, `D;<exit code>`).
+//! `OscScanner` picks these out of the raw output stream, across chunk
+//! boundaries. `mobilecli init <shell>` prints the hooks that emit them,
+//! and with `--auto` also starts every interactive shell under `mobilecli`.
+
+use crate::setup::{self, Settings};
+use std::sync::OnceLock;
+
+/// Set for `mobilecli` started by the auto-wrap snippet
+pub const AUTO_WRAP_ENV: &str = "MOBILECLI_AUTO_WRAP";
+
+/// Exit status of an auto-wrapped `mobilecli` that couldn't start the
+/// session; the snippet then carries on with the plain shell
+pub const AUTO_WRAP_FAILED: u8 = 125;
+
+/// Longest OSC payload kept; anything longer is not shell integration
+const MAX_OSC_LEN: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellEvent {
+    /// The shell's working directory (OSC 7)
+    Cwd(String),
+    /// A prompt is being drawn (OSC 133;A)
+    PromptStart,
+    /// A command line was submitted and is running (OSC 133;C)
+    CommandStarted { command: Option<String> },
+    /// The running command finished (OSC 133;D)
+    CommandFinished { exit_code: Option<i32> },
+}
+
+/// Shells `mobilecli init` has integration scripts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Hooks for `shell` that emit OSC 7 and OSC 133
+pub fn init_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => include_str!("shell/mobilecli.bash"),
+        Shell::Zsh => include_str!("shell/mobilecli.zsh"),
+        Shell::Fish => include_str!("shell/mobilecli.fish"),
+    }
+}
+
+/// Rc-file code that re-runs an interactive shell under `mobilecli` and
+/// exits when it ends. Never inside a session (`MOBILECLI_SESSION_ID` is set
+/// there), for `-c` commands, or over SSH and in IDE terminals unless the
+/// settings allow it.
+pub fn auto_wrap_script(shell: Shell, settings: &Settings) -> String {
+    let mut conditions: Vec<&str> = match shell {
+        Shell::Bash => vec![
+            "[[ $- == *i* && -z ${BASH_EXECUTION_STRING-} ]]",
+            "[[ -z ${MOBILECLI_SESSION_ID-} ]]",
+        ],
+        Shell::Zsh => vec![
+            "[[ -o interactive && -z ${ZSH_EXECUTION_STRING-} ]]",
+            "[[ -z ${MOBILECLI_SESSION_ID-} ]]",
+        ],
+        Shell::Fish => vec!["status is-interactive", "not set -q MOBILECLI_SESSION_ID"],
+    };
+    if !settings.auto_wrap_ssh {
+        conditions.push(match shell {
+            Shell::Bash | Shell::Zsh => "[[ -z ${SSH_CONNECTION-}${SSH_TTY-} ]]",
+            Shell::Fish => "not set -q SSH_CONNECTION; and not set -q SSH_TTY",
+        });
+    }
+    if !settings.auto_wrap_ide {
+        conditions.push(match shell {
+            Shell::Bash | Shell::Zsh => {
+                "[[ -z ${VSCODE_INJECTION-}${INSIDE_EMACS-}${TERMINAL_EMULATOR-} && ${TERM_PROGRAM-} != vscode ]]"
+            }
+            Shell::Fish => {
+                "not set -q VSCODE_INJECTION; and not set -q INSIDE_EMACS; and not set -q TERMINAL_EMULATOR; and test \"$TERM_PROGRAM\" != vscode"
+            }
+        });
+    }
+
+    let header = "# Start interactive shells under mobilecli so they show up on your phone\n";
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            conditions.push("command -v mobilecli >/dev/null 2>&1");
+            let program = if shell == Shell::Bash {
+                "\"$BASH\""
+            } else {
+                "zsh"
+            };
+            format!(
+                "{header}if {conditions}; then\n    \
+                 {env}=1 mobilecli --quiet -- {program}\n    \
+                 # {failed}: mobilecli couldn't start the session; stay in this shell\n    \
+                 rc=$?; [[ $rc == {failed} ]] || exit $rc\n\
+                 fi\n\n",
+                header = header,
+                conditions = conditions.join(" \\\n    && "),
+                env = AUTO_WRAP_ENV,
+                program = program,
+                failed = AUTO_WRAP_FAILED,
+            )
+        }
+        Shell::Fish => {
+            conditions.push("command -q mobilecli");
+            format!(
+                "{header}if {conditions}\n    \
+                 env {env}=1 mobilecli --quiet -- fish\n    \
+                 # {failed}: mobilecli couldn't start the session; stay in this shell\n    \
+                 set -l rc $status\n    \
+                 test $rc -eq {failed}; or exit $rc\n\
+                 end\n\n",
+                header = header,
+                conditions = conditions.join("; and "),
+                env = AUTO_WRAP_ENV,
+                failed = AUTO_WRAP_FAILED,
+            )
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    /// ESC seen inside an OSC; `\` completes the string terminator
+    OscEscape,
+}
+
+/// Incremental OSC parser over raw PTY output
+#[derive(Default)]
+pub struct OscScanner {
+    state: State,
+    payload: Vec<u8>,
+}
+
+impl OscScanner {
+    /// Scan a chunk of output, returning the events it completes
+    pub fn feed(&mut self, data: &[u8]) -> Vec<ShellEvent> {
+        let mut events = Vec::new();
+        if matches!(self.state, State::Ground) && !data.contains(&0x1b) {
+            return events;
+        }
+
+        for &b in data {
+            self.state = match self.state {
+                State::Ground | State::Escape if b == 0x1b => State::Escape,
+                State::Escape if b == b']' => {
+                    self.payload.clear();
+                    State::Osc
+                }
+                State::Ground | State::Escape => State::Ground,
+                State::Osc | State::OscEscape if b == 0x07 || b == b'\\' => {
+                    // BEL ends the OSC anywhere; `\` only after ESC
+                    if b == b'\\' && matches!(self.state, State::Osc) {
+                        self.push(b)
+                    } else {
+                        events.extend(parse_osc(&self.payload));
+                        State::Ground
+                    }
+                }
+                State::Osc if b == 0x1b => State::OscEscape,
+                State::Osc => self.push(b),
+                // ESC followed by anything else abandons the OSC
+                State::OscEscape if b == b']' => {
+                    self.payload.clear();
+                    State::Osc
+                }
+                State::OscEscape => State::Ground,
+            };
+        }
+        events
+    }
+
+    fn push(&mut self, b: u8) -> State {
+        if self.payload.len() >= MAX_OSC_LEN {
+            return State::Ground;
+        }
+        self.payload.push(b);
+        State::Osc
+    }
+}
+
+fn parse_osc(payload: &[u8]) -> Option<ShellEvent> {
+    let payload = std::str::from_utf8(payload).ok()?;
+    let (code, rest) = payload.split_once(';')?;
+    match code {
+        "7" => cwd_from_url(rest).map(ShellEvent::Cwd),
+        "133" => parse_semantic_prompt(rest),
+        _ => None,
+    }
+}
+
+/// OSC 133 `<kind>[;<param>...]`
+fn parse_semantic_prompt(payload: &str) -> Option<ShellEvent> {
+    let mut params = payload.split(';');
+    match params.next()? {
+        "A" => Some(ShellEvent::PromptStart),
+        "C" => Some(ShellEvent::CommandStarted {
+            command: params
+                .find_map(|p| p.strip_prefix("cmdline_url="))
+                .and_then(percent_decode)
+                .filter(|c| !c.is_empty()),
+        }),
+        "D" => Some(ShellEvent::CommandFinished {
+            exit_code: params.next().and_then(|code| code.parse().ok()),
+        }),
+        _ => None,
+    }
+}
+
+/// Path of a `file://host/path` URL, percent-decoded; None if the host is
+/// another machine
+fn cwd_from_url(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("file://")?;
+    let (host, path) = rest.split_at(rest.find('/')?);
+    if !is_local_host(host) {
+        return None;
+    }
+    percent_decode(path)
+}
+
+/// Whether an OSC 7 host names this machine (empty and `localhost` do too).
+/// Only the first label counts: shells report `$HOSTNAME`, which may or may
+/// not be fully qualified.
+fn is_local_host(host: &str) -> bool {
+    static LOCAL: OnceLock<String> = OnceLock::new();
+    let short = |h: &str| h.split('.').next().unwrap_or_default().to_ascii_lowercase();
+    let local = LOCAL.get_or_init(|| short(&setup::get_hostname()));
+    host.is_empty() || host.eq_ignore_ascii_case("localhost") || short(host) == *local
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc7_across_chunks() {
+        let mut scanner = OscScanner::default();
+        assert!(scanner
+            .feed(b"$ ls\r\nfoo\x1b]7;file://localhost/home/u")
+            .is_empty());
+        assert_eq!(
+            scanner.feed(b"ser/my%20dir\x1b\\$ "),
+            vec![ShellEvent::Cwd("/home/user/my dir".into())]
+        );
+        assert_eq!(
+            scanner.feed(b"\x1b[1m\x1b]7;file:///tmp\x07"),
+            vec![ShellEvent::Cwd("/tmp".into())]
+        );
+    }
+
+    #[test]
+    fn test_osc133_command_boundaries() {
+        let mut scanner = OscScanner::default();
+        assert_eq!(
+            scanner.feed(
+                b"\x1b]133;A\x07$ \x1b]133;B\x07make\r\n\x1b]133;C;cmdline_url=make%20-j4\x07"
+            ),
+            vec![
+                ShellEvent::PromptStart,
+                ShellEvent::CommandStarted {
+                    command: Some("make -j4".into())
+                }
+            ]
+        );
+        assert_eq!(
+            scanner.feed(b"done\r\n\x1b]133;D;2\x1b\\\x1b]133;C\x07\x1b]133;D\x07"),
+            vec![
+                ShellEvent::CommandFinished { exit_code: Some(2) },
+                ShellEvent::CommandStarted { command: None },
+                ShellEvent::CommandFinished { exit_code: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_wrap_exits_with_session_status() {
+        let settings = Settings::default();
+        for shell in [Shell::Bash, Shell::Zsh] {
+            let script = auto_wrap_script(shell, &settings);
+            assert!(script.contains("mobilecli --quiet -- "));
+            // The status is saved before the test overwrites $?
+            assert!(script.contains("\n    rc=$?; [[ $rc == 125 ]] || exit $rc\n"));
+        }
+        let script = auto_wrap_script(Shell::Fish, &settings);
+        assert!(script.contains("\n    set -l rc $status\n    test $rc -eq 125; or exit $rc\n"));
+    }
+
+    #[test]
+    fn test_ignores_other_sequences() {
+        let mut scanner = OscScanner::default();
+        assert!(scanner.feed(b"\x1b]0;title\x07\x1b]7;bogus\x07").is_empty());
+        assert!(scanner.feed(b"\x1b]7;file:///a\x1b[0m").is_empty());
+        assert_eq!(
+            scanner.feed(b"\x1b]7;file:///b\x07"),
+            vec![ShellEvent::Cwd("/b".into())]
+        );
+    }
+
+    #[test]
+    fn test_osc7_from_other_hosts_is_ignored() {
+        let local = setup::get_hostname();
+        assert_eq!(
+            cwd_from_url(&format!("file://{}/srv/app", local)).as_deref(),
+            Some("/srv/app")
+        );
+        assert_eq!(
+            cwd_from_url(&format!("file://{}.example.com/srv", local.to_uppercase())).as_deref(),
+            Some("/srv")
+        );
+        assert_eq!(
+            cwd_from_url("file://LocalHost/tmp").as_deref(),
+            Some("/tmp")
+        );
+        assert_eq!(
+            cwd_from_url("file://remote-box-that-is-not-us/home/u"),
+            None
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! PTY size arbitration for shared sessions
+//!
+//! A session is shown on the desktop terminal and possibly on several phones,
+//! each with its own dimensions, but the PTY has exactly one size. The daemon
+//! keeps every attached terminal's size here and derives the effective PTY
+//! size from the session's `SizePolicy`.
+
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Terminal size as (cols, rows)
+pub type Size = (u16, u16);
+
+/// What the wrapper is told when the PTY size or who decides it changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeUpdate {
+    pub size: Size,
+    /// The desktop's own size decides right now, so the wrapper may resize
+    /// the PTY itself when its terminal is resized
+    pub desktop_decides: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizePolicy {
+    /// Always the desktop terminal's size; phones adapt
+    DesktopWins,
+    /// Smallest cols and rows of all attached terminals (like tmux)
+    Smallest,
+    /// Whichever terminal (phone or desktop) most recently resized or typed
+    #[default]
+    ActiveClient,
+    /// A size chosen along with the policy
+    Fixed,
+}
+
+impl SizePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SizePolicy::DesktopWins => "desktop_wins",
+            SizePolicy::Smallest => "smallest",
+            SizePolicy::ActiveClient => "active_client",
+            SizePolicy::Fixed => "fixed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "desktop_wins" => Some(SizePolicy::DesktopWins),
+            "smallest" => Some(SizePolicy::Smallest),
+            "active_client" => Some(SizePolicy::ActiveClient),
+            "fixed" => Some(SizePolicy::Fixed),
+            _ => None,
+        }
+    }
+}
+
+/// Sizes of everything attached to one session
+pub struct SizeArbiter {
+    policy: SizePolicy,
+    fixed: Option<Size>,
+    desktop: Size,
+    /// Mobile viewers' sizes, least recently active first
+    viewers: Vec<(SocketAddr, Size)>,
+    /// Someone typed on the desktop after the last viewer activity
+    desktop_active: bool,
+    /// Size last sent to the PTY
+    applied: Size,
+    /// `desktop_decides` last sent to the wrapper
+    announced_desktop_decides: bool,
+}
+
+impl SizeArbiter {
+    pub fn new(policy: SizePolicy, desktop: Size) -> Self {
+        Self {
+            policy,
+            fixed: None,
+            desktop,
+            viewers: Vec::new(),
+            desktop_active: false,
+            applied: desktop,
+            announced_desktop_decides: policy != SizePolicy::Fixed,
+        }
+    }
+
+    pub fn policy(&self) -> SizePolicy {
+        self.policy
+    }
+
+    /// Change the policy; `fixed` is required for `SizePolicy::Fixed`
+    pub fn set_policy(&mut self, policy: SizePolicy, fixed: Option<Size>) {
+        self.policy = policy;
+        if policy == SizePolicy::Fixed {
+            self.fixed = fixed;
+        }
+    }
+
+    /// The desktop terminal was resized
+    pub fn set_desktop(&mut self, size: Size) {
+        self.desktop = size;
+    }
+
+    /// Someone typed on the desktop; it becomes the active terminal
+    pub fn touch_desktop(&mut self) {
+        self.desktop_active = true;
+    }
+
+    /// A viewer reported its size; this also makes it the active viewer
+    pub fn set_viewer(&mut self, addr: SocketAddr, size: Size) {
+        self.remove_viewer(addr);
+        self.viewers.push((addr, size));
+        self.desktop_active = false;
+    }
+
+    /// A viewer typed into the session; it becomes the active viewer
+    pub fn touch(&mut self, addr: SocketAddr) {
+        if let Some(pos) = self.viewers.iter().position(|(a, _)| *a == addr) {
+            let viewer = self.viewers.remove(pos);
+            self.viewers.push(viewer);
+            self.desktop_active = false;
+        }
+    }
+
+    /// A viewer detached (or asked to stop constraining the size)
+    pub fn remove_viewer(&mut self, addr: SocketAddr) {
+        self.viewers.retain(|(a, _)| *a != addr);
+    }
+
+    /// The size the PTY should have under the current policy
+    pub fn effective(&self) -> Size {
+        match self.policy {
+            SizePolicy::DesktopWins => self.desktop,
+            SizePolicy::Smallest => self
+                .viewers
+                .iter()
+                .fold(self.desktop, |(cols, rows), (_, (c, r))| {
+                    (cols.min(*c), rows.min(*r))
+                }),
+            SizePolicy::ActiveClient => match self.viewers.last() {
+                Some((_, size)) if !self.desktop_active => *size,
+                _ => self.desktop,
+            },
+            SizePolicy::Fixed => self.fixed.unwrap_or(self.desktop),
+        }
+    }
+
+    /// Whether the desktop's size is the effective one whatever it is: no
+    /// viewer can override it under the current policy
+    pub fn desktop_decides(&self) -> bool {
+        match self.policy {
+            SizePolicy::DesktopWins => true,
+            SizePolicy::Smallest => self.viewers.is_empty(),
+            SizePolicy::ActiveClient => self.viewers.is_empty() || self.desktop_active,
+            SizePolicy::Fixed => false,
+        }
+    }
+
+    /// The effective size and who decides it, if either changed since the
+    /// last update
+    pub fn update(&mut self) -> Option<SizeUpdate> {
+        let current = self.current();
+        if current.size == self.applied && current.desktop_decides == self.announced_desktop_decides
+        {
+            return None;
+        }
+        self.applied = current.size;
+        self.announced_desktop_decides = current.desktop_decides;
+        Some(current)
+    }
+
+    /// The effective size and who decides it
+    pub fn current(&self) -> SizeUpdate {
+        SizeUpdate {
+            size: self.effective(),
+            desktop_decides: self.desktop_decides(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_smallest_takes_min_of_each_dimension() {
+        let mut sizes = SizeArbiter::new(SizePolicy::Smallest, (120, 40));
+        sizes.set_viewer(addr(1), (60, 50));
+        sizes.set_viewer(addr(2), (100, 30));
+        assert_eq!(sizes.effective(), (60, 30));
+
+        sizes.remove_viewer(addr(1));
+        assert_eq!(sizes.effective(), (100, 30));
+    }
+
+    #[test]
+    fn test_active_client_follows_most_recent_viewer() {
+        let mut sizes = SizeArbiter::new(SizePolicy::ActiveClient, (120, 40));
+        assert_eq!(sizes.effective(), (120, 40));
+        sizes.set_viewer(addr(1), (60, 20));
+        sizes.set_viewer(addr(2), (80, 30));
+        assert_eq!(sizes.effective(), (80, 30));
+
+        sizes.touch(addr(1));
+        assert_eq!(sizes.effective(), (60, 20));
+        sizes.touch_desktop();
+        assert_eq!(sizes.effective(), (120, 40));
+        sizes.touch(addr(1));
+
+        // Falls back to the remaining viewer, then the desktop
+        sizes.remove_viewer(addr(1));
+        assert_eq!(sizes.effective(), (80, 30));
+        sizes.remove_viewer(addr(2));
+        assert_eq!(sizes.effective(), (120, 40));
+    }
+
+    #[test]
+    fn test_update_reports_only_changes() {
+        let mut sizes = SizeArbiter::new(SizePolicy::DesktopWins, (120, 40));
+        sizes.set_viewer(addr(1), (60, 20));
+        assert_eq!(sizes.update(), None);
+
+        sizes.set_desktop((100, 30));
+        let update = |size, desktop_decides| {
+            Some(SizeUpdate {
+                size,
+                desktop_decides,
+            })
+        };
+        assert_eq!(sizes.update(), update((100, 30), true));
+        assert_eq!(sizes.update(), None);
+
+        sizes.set_policy(SizePolicy::Fixed, Some((80, 24)));
+        assert_eq!(sizes.update(), update((80, 24), false));
+
+        // Who decides can change without the size changing
+        sizes.set_policy(SizePolicy::Smallest, None);
+        sizes.set_desktop((80, 24));
+        sizes.remove_viewer(addr(1));
+        assert_eq!(sizes.update(), update((80, 24), true));
+    }
+}
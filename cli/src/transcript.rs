@@ -0,0 +1,351 @@
+//! Plain-text transcripts of PTY output
+//!
+//! `detection::strip_ansi_and_normalize` only removes escape codes, so
+//! progress bars and redrawn prompts come out as every intermediate frame
+//! glued together. This module replays the output on a simple line model
+//! instead: carriage returns, backspaces, cursor movement and line erases
+//! are applied, and the final text of each line is returned.
+
+/// Tab stop width used when expanding `\t`
+const TAB_WIDTH: usize = 8;
+
+/// Longest line kept; text written past this column is dropped, so cursor
+/// movement with huge parameters can't allocate huge lines
+const MAX_COLS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Ground,
+    Escape,
+    /// ESC ( / ESC ) etc. - skip the charset designator
+    EscapeIntermediate,
+    Csi,
+    Osc,
+    /// Saw ESC inside an OSC (possible ST terminator)
+    OscEscape,
+}
+
+/// Incremental line renderer
+///
+/// Lines are logical (no wrapping at the terminal width); `rows` is the
+/// screen height used to resolve absolute cursor positioning.
+pub struct LineRenderer {
+    lines: Vec<Vec<char>>,
+    row: usize,
+    col: usize,
+    /// Index of the first line of the visible screen
+    screen_top: usize,
+    rows: usize,
+    state: ParseState,
+    params: String,
+}
+
+impl LineRenderer {
+    pub fn new(rows: u16) -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            row: 0,
+            col: 0,
+            screen_top: 0,
+            rows: rows.max(1) as usize,
+            state: ParseState::Ground,
+            params: String::new(),
+        }
+    }
+
+    /// Feed decoded PTY output
+    pub fn feed(&mut self, text: &str) {
+        for c in text.chars() {
+            self.feed_char(c);
+        }
+    }
+
+    fn feed_char(&mut self, c: char) {
+        match self.state {
+            ParseState::Ground => self.ground(c),
+            ParseState::Escape => match c {
+                '[' => {
+                    self.params.clear();
+                    self.state = ParseState::Csi;
+                }
+                ']' => self.state = ParseState::Osc,
+                '(' | ')' | '*' | '+' | '#' | '%' => self.state = ParseState::EscapeIntermediate,
+                'D' | 'E' => {
+                    // IND / NEL
+                    if c == 'E' {
+                        self.col = 0;
+                    }
+                    self.line_feed();
+                    self.state = ParseState::Ground;
+                }
+                'M' => {
+                    // Reverse index
+                    self.row = self.row.saturating_sub(1).max(self.screen_top);
+                    self.state = ParseState::Ground;
+                }
+                _ => self.state = ParseState::Ground,
+            },
+            ParseState::EscapeIntermediate => self.state = ParseState::Ground,
+            ParseState::Csi => {
+                if ('\u{40}'..='\u{7e}').contains(&c) {
+                    let params = std::mem::take(&mut self.params);
+                    self.csi(c, &params);
+                    self.state = ParseState::Ground;
+                } else {
+                    self.params.push(c);
+                }
+            }
+            ParseState::Osc => match c {
+                '\x07' => self.state = ParseState::Ground,
+                '\x1b' => self.state = ParseState::OscEscape,
+                _ => {}
+            },
+            ParseState::OscEscape => {
+                self.state = if c == '\\' {
+                    ParseState::Ground
+                } else {
+                    ParseState::Osc
+                };
+            }
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.state = ParseState::Escape,
+            '\r' => self.col = 0,
+            // The PTY normally sends \r\n; treat a bare \n as a new line too
+            '\n' | '\x0b' | '\x0c' => {
+                self.col = 0;
+                self.line_feed();
+            }
+            '\x08' => self.col = self.col.saturating_sub(1),
+            '\t' => self.set_col((self.col / TAB_WIDTH + 1) * TAB_WIDTH),
+            c if c.is_control() => {}
+            c => self.put(c),
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.col >= MAX_COLS {
+            return;
+        }
+        let line = &mut self.lines[self.row];
+        if line.len() < self.col {
+            line.resize(self.col, ' ');
+        }
+        if self.col < line.len() {
+            line[self.col] = c;
+        } else {
+            line.push(c);
+        }
+        self.col += 1;
+    }
+
+    fn line_feed(&mut self) {
+        self.row += 1;
+        self.ensure_row();
+        // Scroll the screen window along with the output
+        if self.row >= self.screen_top + self.rows {
+            self.screen_top = self.row + 1 - self.rows;
+        }
+    }
+
+    fn ensure_row(&mut self) {
+        while self.lines.len() <= self.row {
+            self.lines.push(Vec::new());
+        }
+    }
+
+    /// Move the cursor to `row`, kept on the visible screen
+    fn set_row(&mut self, row: usize) {
+        self.row = row.clamp(self.screen_top, self.screen_top + self.rows - 1);
+        self.ensure_row();
+    }
+
+    fn set_col(&mut self, col: usize) {
+        self.col = col.min(MAX_COLS);
+    }
+
+    fn csi(&mut self, final_char: char, params: &str) {
+        // Private modes (?1049h etc.) and other intermediates don't affect text
+        if params.starts_with(['?', '>', '<', '=']) {
+            return;
+        }
+        let nums: Vec<usize> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let n = nums.first().copied().unwrap_or(0).max(1);
+
+        match final_char {
+            'A' => self.row = self.row.saturating_sub(n).max(self.screen_top),
+            'B' | 'e' => self.set_row(self.row.saturating_add(n)),
+            'C' | 'a' => self.set_col(self.col.saturating_add(n)),
+            'D' => self.col = self.col.saturating_sub(n),
+            'E' => {
+                self.set_row(self.row.saturating_add(n));
+                self.col = 0;
+            }
+            'F' => {
+                self.row = self.row.saturating_sub(n).max(self.screen_top);
+                self.col = 0;
+            }
+            'G' | '`' => self.set_col(n - 1),
+            'd' => self.set_row(self.screen_top.saturating_add(n - 1)),
+            'H' | 'f' => {
+                let row = nums.first().copied().unwrap_or(0).max(1);
+                let col = nums.get(1).copied().unwrap_or(0).max(1);
+                self.set_row(self.screen_top.saturating_add(row - 1));
+                self.set_col(col - 1);
+            }
+            'K' => {
+                let line = &mut self.lines[self.row];
+                match nums.first().copied().unwrap_or(0) {
+                    0 => line.truncate(self.col),
+                    1 => {
+                        for ch in line.iter_mut().take(self.col + 1) {
+                            *ch = ' ';
+                        }
+                    }
+                    _ => line.clear(),
+                }
+            }
+            'J' => match nums.first().copied().unwrap_or(0) {
+                0 => {
+                    self.lines[self.row].truncate(self.col);
+                    self.lines.truncate(self.row + 1);
+                }
+                1 => {}
+                _ => {
+                    // Clear screen: what was visible becomes history and a
+                    // fresh screen starts below it
+                    self.trim_trailing_empty();
+                    let cursor_offset = self.row.saturating_sub(self.screen_top);
+                    self.screen_top = self.lines.len();
+                    self.row = self.screen_top + cursor_offset;
+                    self.ensure_row();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn trim_trailing_empty(&mut self) {
+        while self.lines.len() > 1
+            && self
+                .lines
+                .last()
+                .is_some_and(|l| l.iter().all(|c| *c == ' '))
+        {
+            self.lines.pop();
+        }
+    }
+
+    /// Drain lines that have scrolled above the screen; cursor movement can
+    /// no longer reach them, so their text is final
+    pub fn take_committed(&mut self) -> Vec<String> {
+        if self.screen_top == 0 {
+            return Vec::new();
+        }
+        let committed: Vec<String> = self
+            .lines
+            .drain(..self.screen_top)
+            .map(|l| l.into_iter().collect::<String>().trim_end().to_string())
+            .collect();
+        self.row -= self.screen_top;
+        self.screen_top = 0;
+        self.ensure_row();
+        committed
+    }
+
+    /// Rendered lines with trailing whitespace and trailing blank lines removed
+    pub fn lines(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .lines
+            .iter()
+            .map(|l| l.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        while out.last().is_some_and(|l| l.is_empty()) {
+            out.pop();
+        }
+        out
+    }
+}
+
+/// Render raw PTY bytes into plain-text lines
+pub fn render_lines(bytes: &[u8], rows: u16) -> Vec<String> {
+    let mut renderer = LineRenderer::new(rows);
+    renderer.feed(&String::from_utf8_lossy(bytes));
+    renderer.lines()
+}
+
+/// Keep only the last `n` lines
+pub fn tail_lines(mut lines: Vec<String>, n: usize) -> Vec<String> {
+    if lines.len() > n {
+        lines.drain(..lines.len() - n);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carriage_return_overwrites() {
+        let lines = render_lines(b"Progress 10%\rProgress 55%\rProgress 100%\r\ndone\r\n", 24);
+        assert_eq!(lines, vec!["Progress 100%", "done"]);
+    }
+
+    #[test]
+    fn test_escape_codes_removed() {
+        let lines = render_lines(b"\x1b[1;32mok\x1b[0m \x1b]0;title\x07test\r\n", 24);
+        assert_eq!(lines, vec!["ok test"]);
+    }
+
+    #[test]
+    fn test_backspace_and_erase_line() {
+        let lines = render_lines(b"helo\x08\x08llo\r\nabcdef\r\x1b[Kxyz\r\n", 24);
+        assert_eq!(lines, vec!["hello", "xyz"]);
+    }
+
+    #[test]
+    fn test_cursor_up_redraw() {
+        // Spinner-style redraw: move up one line, clear it, write the final state
+        let lines = render_lines(
+            b"step 1\r\n\xe2\xa0\x8b working\r\n\x1b[1A\x1b[2Kfinished\r\n",
+            24,
+        );
+        assert_eq!(lines, vec!["step 1", "finished"]);
+    }
+
+    #[test]
+    fn test_take_committed_keeps_screen() {
+        let mut renderer = LineRenderer::new(2);
+        renderer.feed("one\r\ntwo\r\nthree\r\nfour");
+        assert_eq!(renderer.take_committed(), vec!["one", "two"]);
+        assert!(renderer.take_committed().is_empty());
+        // Cursor movement still works on the remaining screen lines
+        renderer.feed("\x1b[1A\r\x1b[2KTHREE");
+        assert_eq!(renderer.lines(), vec!["THREE", "four"]);
+    }
+
+    #[test]
+    fn test_huge_cursor_moves_are_clamped() {
+        let mut renderer = LineRenderer::new(3);
+        renderer.feed("top\x1b[99999999B\x1b[18446744073709551615Cx");
+        renderer.feed("\x1b[18446744073709551615;18446744073709551615Hy");
+        renderer.feed("\x1b[18446744073709551615d\x1b[99999999Gz\x1b[3Gw");
+        assert_eq!(renderer.lines.len(), 3);
+        assert!(renderer.lines.iter().all(|l| l.len() <= MAX_COLS));
+        assert_eq!(renderer.lines(), vec!["top", "", "  w"]);
+
+        // Cursor position report style absolute moves stay on screen
+        let lines = render_lines(b"a\r\nb\x1b[1;1HA\x1b[50;1HB", 2);
+        assert_eq!(lines, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let lines = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(tail_lines(lines, 2), vec!["b", "c"]);
+    }
+}
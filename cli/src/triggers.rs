@@ -0,0 +1,244 @@
+//! User-defined regex triggers on session output
+//!
+//! Besides the built-in wait detection, sessions can watch their output for
+//! patterns of the user's choosing: rules from the `triggers` setting apply
+//! to every session (optionally only those running a given CLI), and
+//! `mobilecli watch-for` adds more to a single session. Patterns are matched
+//! against the same ANSI-stripped, normalized buffer as wait detection, and
+//! only matches that reach into newly arrived output count. After firing, a
+//! trigger stays quiet for its cooldown so a flood of errors is one message.
+
+use crate::detection::CliType;
+use crate::protocol::TriggerItem;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Cooldown for triggers that don't set one
+pub const DEFAULT_COOLDOWN_SECS: u64 = 60;
+
+/// Upper bound on a compiled pattern, so one rule can't eat the daemon's memory
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Longest `line` reported with a match
+const MAX_LINE_CHARS: usize = 200;
+
+/// A trigger as written in the settings or sent by a client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerRule {
+    /// Shown instead of the pattern in notifications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub pattern: String,
+    /// Only sessions running this CLI ("claude", "codex", "gemini",
+    /// "opencode", "terminal"); all sessions if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cli: Option<String>,
+    /// Also send a push notification when it fires
+    #[serde(default)]
+    pub push: bool,
+    /// Minimum time between two firings (default 60s)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+}
+
+struct Trigger {
+    id: u64,
+    rule: TriggerRule,
+    regex: Regex,
+    from_config: bool,
+    last_fired: Option<Instant>,
+}
+
+impl Trigger {
+    fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.rule.cooldown_secs.unwrap_or(DEFAULT_COOLDOWN_SECS))
+    }
+}
+
+/// A trigger that fired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerHit {
+    pub id: u64,
+    pub name: Option<String>,
+    pub pattern: String,
+    pub push: bool,
+    /// The matched text
+    pub matched: String,
+    /// The output line the match is on
+    pub line: String,
+}
+
+impl TriggerHit {
+    /// Name of the trigger, or its pattern if it has none
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.pattern)
+    }
+}
+
+/// A session's triggers
+#[derive(Default)]
+pub struct TriggerSet {
+    triggers: Vec<Trigger>,
+    next_id: u64,
+}
+
+impl TriggerSet {
+    /// Triggers from the settings; rules with invalid patterns are skipped
+    pub fn from_rules(rules: &[TriggerRule]) -> Self {
+        let mut set = Self::default();
+        for rule in rules {
+            if let Err(e) = set.add(rule.clone(), true) {
+                tracing::warn!("Ignoring trigger /{}/: {}", rule.pattern, e);
+            }
+        }
+        set
+    }
+
+    /// Add a trigger; returns its ID
+    pub fn add(&mut self, rule: TriggerRule, from_config: bool) -> Result<u64, regex::Error> {
+        let regex = RegexBuilder::new(&rule.pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()?;
+        self.next_id += 1;
+        self.triggers.push(Trigger {
+            id: self.next_id,
+            rule,
+            regex,
+            from_config,
+            last_fired: None,
+        });
+        Ok(self.next_id)
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.triggers.len();
+        self.triggers.retain(|t| t.id != id);
+        self.triggers.len() != before
+    }
+
+    /// Triggers matching `buffer` past byte offset `new_from` (where the
+    /// latest output starts), other than those cooling down
+    pub fn check(&mut self, buffer: &str, new_from: usize, cli_type: CliType) -> Vec<TriggerHit> {
+        let now = Instant::now();
+        let mut hits = Vec::new();
+        for trigger in &mut self.triggers {
+            if trigger
+                .rule
+                .cli
+                .as_deref()
+                .is_some_and(|cli| cli != cli_type.as_str())
+            {
+                continue;
+            }
+            if trigger
+                .last_fired
+                .is_some_and(|at| now.duration_since(at) < trigger.cooldown())
+            {
+                continue;
+            }
+            let Some(m) = trigger.regex.find_iter(buffer).find(|m| m.end() > new_from) else {
+                continue;
+            };
+            trigger.last_fired = Some(now);
+            hits.push(TriggerHit {
+                id: trigger.id,
+                name: trigger.rule.name.clone(),
+                pattern: trigger.rule.pattern.clone(),
+                push: trigger.rule.push,
+                matched: m.as_str().to_string(),
+                line: line_around(buffer, m.start(), m.end()),
+            });
+        }
+        hits
+    }
+
+    pub fn items(&self) -> Vec<TriggerItem> {
+        self.triggers
+            .iter()
+            .map(|t| TriggerItem {
+                id: t.id,
+                name: t.rule.name.clone(),
+                pattern: t.rule.pattern.clone(),
+                cli: t.rule.cli.clone(),
+                push: t.rule.push,
+                cooldown_secs: t.cooldown().as_secs(),
+                from_config: t.from_config,
+            })
+            .collect()
+    }
+}
+
+/// The line(s) containing `buffer[start..end]`, trimmed and shortened
+fn line_around(buffer: &str, start: usize, end: usize) -> String {
+    let line_start = buffer[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = buffer[end..].find('\n').map_or(buffer.len(), |i| end + i);
+    buffer[line_start..line_end]
+        .trim()
+        .chars()
+        .take(MAX_LINE_CHARS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str) -> TriggerRule {
+        TriggerRule {
+            name: None,
+            pattern: pattern.into(),
+            cli: None,
+            push: false,
+            cooldown_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_matches_only_new_output() {
+        let mut set = TriggerSet::default();
+        set.add(rule(r"error\[E\d+\]"), false).unwrap();
+
+        let buffer = "Compiling foo\nerror[E0308]: mismatched types\n";
+        let hits = set.check(buffer, 0, CliType::Terminal);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].matched, "error[E0308]");
+        assert_eq!(hits[0].line, "error[E0308]: mismatched types");
+
+        // Same text, nothing new after it: no match even without the cooldown
+        let mut set = TriggerSet::default();
+        set.add(rule(r"error\[E\d+\]"), false).unwrap();
+        let more = format!("{}warning: unused\n", buffer);
+        assert!(set.check(&more, buffer.len(), CliType::Terminal).is_empty());
+
+        // A match completed by the new chunk counts
+        let split = "error[E03";
+        let done = format!("{}08]: oops", split);
+        assert_eq!(set.check(&done, split.len(), CliType::Terminal).len(), 1);
+    }
+
+    #[test]
+    fn test_cooldown_and_cli_filter() {
+        let mut set = TriggerSet::default();
+        set.add(rule("panicked"), false).unwrap();
+        set.add(
+            TriggerRule {
+                cli: Some("claude".into()),
+                cooldown_secs: Some(0),
+                ..rule("panicked")
+            },
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(set.check("thread panicked", 0, CliType::Claude).len(), 2);
+        // The default trigger is cooling down; the other has no cooldown
+        let hits = set.check("thread panicked", 0, CliType::Claude);
+        assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![2]);
+        assert!(set.check("thread panicked", 0, CliType::Codex).is_empty());
+
+        assert!(set.remove(1));
+        assert_eq!(set.items().len(), 1);
+        assert!(set.add(rule("("), false).is_err());
+    }
+}
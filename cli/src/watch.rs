@@ -0,0 +1,533 @@
+//! Watch command - full-screen dashboard of several sessions
+//!
+//! Tiles every running session in a grid. Each pane is rendered from the
+//! daemon's output stream through its own VT parser, sessions waiting for
+//! input are highlighted, tool prompts can be answered with a keypress, and
+//! Enter jumps into `mobilecli link` for the focused pane.
+
+use crate::client::{self, ClientError, WsStream};
+use crate::link;
+use crate::platform;
+use crate::protocol::{ClientMessage, ServerMessage, SessionListItem};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use colored::Colorize;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use std::io::{self, Write};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Redraw at most this often while output streams in
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+type WsSink = SplitSink<WsStream, Message>;
+
+/// Run the watch command; returns to the dashboard after each link
+pub async fn run() -> Result<(), ClientError> {
+    // One reader for the dashboard and the links it opens
+    let mut input_rx = link::spawn_stdin_reader();
+    while let Some(session_id) = run_dashboard(&mut input_rx).await? {
+        link::attach(&session_id, &mut input_rx).await?;
+    }
+    Ok(())
+}
+
+/// Show the dashboard until the user quits (None) or picks a pane to link
+async fn run_dashboard(
+    input_rx: &mut UnboundedReceiver<Vec<u8>>,
+) -> Result<Option<String>, ClientError> {
+    let (ws, sessions) = client::connect().await?;
+    let (mut tx, mut rx) = ws.split();
+
+    // Subscribing gets each session's current screen; output for all
+    // sessions is streamed either way
+    let mut dash = Dashboard::default();
+    for session_id in dash.set_sessions(sessions) {
+        send(&mut tx, &ClientMessage::Subscribe { session_id }).await?;
+    }
+
+    let _terminal = TerminalGuard::enter()?;
+
+    let mut resize_signal = platform::ResizeSignal::new();
+    let mut frame = tokio::time::interval(FRAME_INTERVAL);
+    let mut dirty = true;
+    let mut clear = true;
+    let mut link_to = None;
+
+    loop {
+        tokio::select! {
+            result = rx.next() => {
+                match result {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) {
+                            for session_id in dash.handle(msg) {
+                                send(&mut tx, &ClientMessage::Subscribe { session_id }).await?;
+                            }
+                            dirty = true;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    _ => {}
+                }
+            }
+
+            Some(input) = input_rx.recv() => {
+                for key in parse_keys(&input) {
+                    match key {
+                        Key::Next => dash.move_focus(1),
+                        Key::Prev => dash.move_focus(-1),
+                        Key::Jump(n) if n < dash.panes.len() => dash.focus = n,
+                        Key::Jump(_) => {}
+                        Key::Approve(response) => {
+                            dash.message = match dash.focused().filter(|p| p.waiting.is_some()) {
+                                Some(pane) => {
+                                    let msg = ClientMessage::ToolApproval {
+                                        session_id: pane.session.session_id.clone(),
+                                        response: response.to_string(),
+                                    };
+                                    send(&mut tx, &msg).await?;
+                                    Some(format!("Sent '{}' to {}", response, pane.session.name))
+                                }
+                                None => Some("Nothing to approve in this pane".to_string()),
+                            };
+                        }
+                        Key::Link => {
+                            if let Some(pane) = dash.focused() {
+                                link_to = Some(pane.session.session_id.clone());
+                            }
+                        }
+                        Key::Quit => return Ok(None),
+                    }
+                }
+                if link_to.is_some() {
+                    break;
+                }
+                dirty = true;
+            }
+
+            _ = resize_signal.recv() => {
+                clear = true;
+                dirty = true;
+            }
+
+            _ = frame.tick() => {
+                if dirty {
+                    let (cols, rows) = platform::terminal_size();
+                    let mut out = Vec::new();
+                    if clear {
+                        out.extend_from_slice(b"\x1b[2J");
+                        clear = false;
+                    }
+                    dash.render(&mut out, cols, rows);
+                    let mut stdout = io::stdout();
+                    let _ = stdout.write_all(&out);
+                    let _ = stdout.flush();
+                    dirty = false;
+                }
+            }
+        }
+    }
+
+    let _ = tx.close().await;
+    Ok(link_to)
+}
+
+async fn send(tx: &mut WsSink, msg: &ClientMessage) -> Result<(), ClientError> {
+    tx.send(Message::Text(serde_json::to_string(msg)?)).await?;
+    Ok(())
+}
+
+/// Raw mode on the alternate screen for as long as the dashboard is shown
+struct TerminalGuard {
+    #[cfg(unix)]
+    original: nix::sys::termios::Termios,
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        #[cfg(unix)]
+        let original = {
+            use std::os::unix::io::AsRawFd;
+            link::setup_raw_mode(io::stdin().as_raw_fd())?
+        };
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        stdout.flush()?;
+        Ok(Self {
+            #[cfg(unix)]
+            original,
+        })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            let _ = link::restore_terminal_mode(io::stdin().as_raw_fd(), &self.original);
+        }
+    }
+}
+
+struct Pane {
+    session: SessionListItem,
+    screen: vt100::Parser,
+    /// Wait type while the session is waiting for input
+    waiting: Option<String>,
+}
+
+#[derive(Default)]
+struct Dashboard {
+    panes: Vec<Pane>,
+    focus: usize,
+    /// Shown in the footer until the next key
+    message: Option<String>,
+}
+
+impl Dashboard {
+    /// Replace the session list, keeping existing panes. Returns the IDs of
+    /// sessions that are new (to subscribe to).
+    fn set_sessions(&mut self, sessions: Vec<SessionListItem>) -> Vec<String> {
+        let focused = self.focused().map(|p| p.session.session_id.clone());
+        let mut old: Vec<Pane> = std::mem::take(&mut self.panes);
+        let mut added = Vec::new();
+
+        for session in sessions {
+            match old
+                .iter()
+                .position(|p| p.session.session_id == session.session_id)
+            {
+                Some(pos) => {
+                    let mut pane = old.swap_remove(pos);
+                    pane.session = session;
+                    self.panes.push(pane);
+                }
+                None => {
+                    added.push(session.session_id.clone());
+                    self.panes.push(Pane {
+                        session,
+                        screen: vt100::Parser::new(24, 80, 0),
+                        waiting: None,
+                    });
+                }
+            }
+        }
+
+        self.focus = focused
+            .and_then(|id| self.panes.iter().position(|p| p.session.session_id == id))
+            .unwrap_or(0);
+        added
+    }
+
+    fn pane_mut(&mut self, session_id: &str) -> Option<&mut Pane> {
+        self.panes
+            .iter_mut()
+            .find(|p| p.session.session_id == session_id)
+    }
+
+    fn focused(&self) -> Option<&Pane> {
+        self.panes.get(self.focus)
+    }
+
+    fn move_focus(&mut self, delta: isize) {
+        if !self.panes.is_empty() {
+            let n = self.panes.len() as isize;
+            self.focus = (self.focus as isize + delta).rem_euclid(n) as usize;
+        }
+        self.message = None;
+    }
+
+    /// Apply a daemon message; returns sessions to subscribe to
+    fn handle(&mut self, msg: ServerMessage) -> Vec<String> {
+        match msg {
+            ServerMessage::Sessions { sessions } => return self.set_sessions(sessions),
+            ServerMessage::PtyBytes { session_id, data } => {
+                if let (Some(pane), Ok(bytes)) = (self.pane_mut(&session_id), BASE64.decode(data)) {
+                    pane.screen.process(&bytes);
+                }
+            }
+            ServerMessage::ScreenSnapshot {
+                session_id,
+                cols,
+                rows,
+                data,
+                ..
+            } => {
+                if let (Some(pane), Ok(bytes)) = (self.pane_mut(&session_id), BASE64.decode(data)) {
+                    pane.screen = vt100::Parser::new(rows.max(1), cols.max(1), 0);
+                    pane.screen.process(&bytes);
+                }
+            }
+            ServerMessage::PtyResized {
+                session_id,
+                cols,
+                rows,
+            } => {
+                if let Some(pane) = self.pane_mut(&session_id) {
+                    pane.screen.set_size(rows.max(1), cols.max(1));
+                }
+            }
+            ServerMessage::WaitingForInput {
+                session_id,
+                wait_type,
+                ..
+            } => {
+                if let Some(pane) = self.pane_mut(&session_id) {
+                    pane.waiting = Some(wait_type);
+                }
+            }
+            ServerMessage::WaitingCleared { session_id, .. } => {
+                if let Some(pane) = self.pane_mut(&session_id) {
+                    pane.waiting = None;
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn render(&self, out: &mut Vec<u8>, cols: u16, rows: u16) {
+        let (cols, rows) = (cols as usize, rows as usize);
+        if self.panes.is_empty() {
+            out.extend_from_slice(b"\x1b[2J\x1b[H");
+            let _ = write!(out, "{}", "No active sessions. Waiting...".dimmed());
+        }
+
+        let body_rows = rows.saturating_sub(1);
+        for (i, (pane, rect)) in self
+            .panes
+            .iter()
+            .zip(layout(self.panes.len(), cols, body_rows))
+            .enumerate()
+        {
+            pane.render(out, i, rect, i == self.focus);
+        }
+
+        // Footer
+        let help = "Tab/←→ focus  1-9 jump  y/a/n approve  Enter link  q quit";
+        let footer = match &self.message {
+            Some(message) => format!("{}  │  {}", message, help),
+            None => help.to_string(),
+        };
+        let _ = write!(
+            out,
+            "\x1b[{};1H{}",
+            rows.max(1),
+            fit(&footer, cols).reversed()
+        );
+    }
+}
+
+impl Pane {
+    fn render(&self, out: &mut Vec<u8>, index: usize, rect: Rect, focused: bool) {
+        if rect.w < 4 || rect.h < 3 {
+            return;
+        }
+        let inner_w = rect.w - 2;
+        let inner_h = rect.h - 2;
+        let paint = |s: &str| {
+            if focused {
+                s.cyan().bold().to_string()
+            } else if self.waiting.is_some() {
+                s.yellow().to_string()
+            } else {
+                s.dimmed().to_string()
+            }
+        };
+
+        // Top border with title
+        let mut title = format!(
+            " {} {} [{}] ",
+            index + 1,
+            self.session.name,
+            self.session.cli_type
+        );
+        if let Some(wait_type) = &self.waiting {
+            title.push_str(&format!("⏳ {} ", wait_type));
+        }
+        let title = fit(&title, inner_w.saturating_sub(1));
+        let fill = inner_w.saturating_sub(1 + title.chars().count());
+        let title = if self.waiting.is_some() {
+            title.black().on_yellow().to_string()
+        } else {
+            paint(&title)
+        };
+        let _ = write!(
+            out,
+            "\x1b[{};{}H{}{}{}",
+            rect.y + 1,
+            rect.x + 1,
+            paint("┌─"),
+            title,
+            paint(&format!("{}┐", "─".repeat(fill)))
+        );
+
+        // Screen contents: the rows up to the cursor that fit in the pane
+        let screen = self.screen.screen();
+        let (cursor_row, _) = screen.cursor_position();
+        let start = (cursor_row as usize + 1).saturating_sub(inner_h);
+        let mut lines = screen.rows(0, inner_w as u16).skip(start);
+        for row in 0..inner_h {
+            let line = lines.next().unwrap_or_default();
+            let _ = write!(
+                out,
+                "\x1b[{};{}H{}{}{}",
+                rect.y + 2 + row,
+                rect.x + 1,
+                paint("│"),
+                fit(&line, inner_w),
+                paint("│")
+            );
+        }
+
+        let _ = write!(
+            out,
+            "\x1b[{};{}H{}",
+            rect.y + rect.h,
+            rect.x + 1,
+            paint(&format!("└{}┘", "─".repeat(inner_w)))
+        );
+    }
+}
+
+/// Truncate or pad `s` to exactly `width` characters
+fn fit(s: &str, width: usize) -> String {
+    let mut out: String = s.chars().filter(|c| !c.is_control()).take(width).collect();
+    let len = out.chars().count();
+    out.extend(std::iter::repeat(' ').take(width - len));
+    out
+}
+
+/// A pane's position on screen (0-based)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+/// Tile `n` panes over a `cols` x `rows` area: a near-square grid whose last
+/// row stretches its panes to the full width
+fn layout(n: usize, cols: usize, rows: usize) -> Vec<Rect> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let grid_cols = (1..=n).find(|c| c * c >= n).unwrap_or(n);
+    let grid_rows = (n + grid_cols - 1) / grid_cols;
+
+    let mut rects = Vec::with_capacity(n);
+    for r in 0..grid_rows {
+        let in_row = grid_cols.min(n - r * grid_cols);
+        let y = r * rows / grid_rows;
+        let h = (r + 1) * rows / grid_rows - y;
+        for c in 0..in_row {
+            let x = c * cols / in_row;
+            let w = (c + 1) * cols / in_row - x;
+            rects.push(Rect { x, y, w, h });
+        }
+    }
+    rects
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Next,
+    Prev,
+    /// Focus pane N (0-based)
+    Jump(usize),
+    /// Answer a tool prompt: "yes" | "yes_always" | "no"
+    Approve(&'static str),
+    Link,
+    Quit,
+}
+
+fn parse_keys(data: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        let (key, len) = if rest.starts_with(b"\x1b[C") {
+            (Some(Key::Next), 3)
+        } else if rest.starts_with(b"\x1b[D") || rest.starts_with(b"\x1b[Z") {
+            (Some(Key::Prev), 3)
+        } else {
+            let key = match rest[0] {
+                b'\t' | b'l' => Some(Key::Next),
+                b'h' => Some(Key::Prev),
+                b @ b'1'..=b'9' => Some(Key::Jump((b - b'1') as usize)),
+                b'y' => Some(Key::Approve("yes")),
+                b'a' => Some(Key::Approve("yes_always")),
+                b'n' => Some(Key::Approve("no")),
+                b'\r' | b'\n' => Some(Key::Link),
+                b'q' | 0x03 => Some(Key::Quit),
+                _ => None,
+            };
+            (key, 1)
+        };
+        keys.extend(key);
+        i += len;
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_tiles_whole_area() {
+        let rects = layout(3, 100, 40);
+        assert_eq!(
+            rects,
+            vec![
+                Rect {
+                    x: 0,
+                    y: 0,
+                    w: 50,
+                    h: 20
+                },
+                Rect {
+                    x: 50,
+                    y: 0,
+                    w: 50,
+                    h: 20
+                },
+                Rect {
+                    x: 0,
+                    y: 20,
+                    w: 100,
+                    h: 20
+                },
+            ]
+        );
+        assert_eq!(
+            layout(1, 80, 24),
+            vec![Rect {
+                x: 0,
+                y: 0,
+                w: 80,
+                h: 24
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"\x1b[C2y\x1b[Zq"),
+            vec![
+                Key::Next,
+                Key::Jump(1),
+                Key::Approve("yes"),
+                Key::Prev,
+                Key::Quit
+            ]
+        );
+    }
+}